@@ -0,0 +1,219 @@
+use std::ffi::OsString;
+
+use artisan_middleware::dusa_collection_utils::{core::logger::LogLevel, log};
+use clap::{Arg, CommandFactory};
+use tabled::Table;
+use toml::{Table as TomlTable, Value};
+
+use crate::{
+    cli::Cli,
+    config::{config, update_config},
+    defs::AliasRow,
+    formatting::{display_table, style_table},
+    run::split_words,
+};
+
+/// Fills `$1`..`$9` in `expansion` with the arguments after the alias and
+/// `$@` with all of them. Arguments no placeholder used are appended, so
+/// plain aliases work like git's.
+fn substitute(name: &str, expansion: &str, args: &[String]) -> Result<Vec<String>, String> {
+    let words = split_words(expansion).map_err(|err| format!("alias {}: {}", name, err))?;
+    let mut used = vec![false; args.len()];
+    let mut expanded = Vec::new();
+
+    for word in words {
+        if word == "$@" {
+            expanded.extend(args.iter().cloned());
+            used.fill(true);
+            continue;
+        }
+
+        // placeholders can sit inside a word, e.g. `--filter=runner=$1`
+        let mut filled = String::new();
+        let mut chars = word.chars().peekable();
+        while let Some(ch) = chars.next() {
+            let position = chars
+                .peek()
+                .and_then(|next| next.to_digit(10))
+                .filter(|digit| *digit > 0);
+            match position {
+                Some(position) if ch == '$' => {
+                    chars.next();
+                    let index = position as usize - 1;
+                    let arg = args.get(index).ok_or_else(|| {
+                        format!("alias {} needs at least {} argument(s)", name, position)
+                    })?;
+                    used[index] = true;
+                    filled.push_str(arg);
+                }
+                _ => filled.push(ch),
+            }
+        }
+        expanded.push(filled);
+    }
+
+    expanded.extend(
+        args.iter()
+            .zip(&used)
+            .filter(|(_, used)| !**used)
+            .map(|(arg, _)| arg.clone()),
+    );
+    Ok(expanded)
+}
+
+/// Replaces the alias at `words[at]`, if it is one, with what it stands
+/// for. Aliases may use other aliases, loops are an error.
+pub fn expand_aliases(mut words: Vec<String>, at: usize) -> Result<Vec<String>, String> {
+    let aliases = &config().alias;
+    let mut seen = Vec::new();
+
+    while let Some(expansion) = words.get(at).and_then(|word| aliases.get(word)) {
+        let name = words[at].clone();
+        if seen.contains(&name) {
+            seen.push(name);
+            return Err(format!("alias loop: {}", seen.join(" -> ")));
+        }
+        let args = words.split_off(at + 1);
+        words.pop();
+        words.extend(substitute(&name, expansion, &args)?);
+        seen.push(name);
+    }
+
+    Ok(words)
+}
+
+/// Where the subcommand sits in the process arguments, after the binary
+/// name and any global flags (and their values).
+fn command_index(args: &[OsString]) -> Option<usize> {
+    let mut cli = Cli::command();
+    cli.build();
+    let takes_value = |arg: Option<&Arg>| arg.is_some_and(|arg| arg.get_action().takes_values());
+
+    let mut index = 1;
+    while let Some(arg) = args.get(index) {
+        let arg = arg.to_str()?;
+        if arg == "--" {
+            return None;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            let known = cli.get_arguments().find(|a| a.get_long() == Some(long));
+            if !long.contains('=') && takes_value(known) {
+                index += 1;
+            }
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|s| !s.is_empty()) {
+            // a short that takes a value has it attached or in the next argument
+            for (offset, short) in shorts.char_indices() {
+                if takes_value(cli.get_arguments().find(|a| a.get_short() == Some(short))) {
+                    if offset + short.len_utf8() == shorts.len() {
+                        index += 1;
+                    }
+                    break;
+                }
+            }
+        } else {
+            return Some(index);
+        }
+        index += 1;
+    }
+    None
+}
+
+/// `expand_aliases` for the process arguments, run before they reach clap.
+pub fn expand_args(mut args: Vec<OsString>) -> Result<Vec<OsString>, Box<dyn std::error::Error>> {
+    let Some(at) = command_index(&args) else {
+        return Ok(args);
+    };
+    let is_alias = args[at]
+        .to_str()
+        .is_some_and(|word| config().alias.contains_key(word));
+    if !is_alias {
+        return Ok(args);
+    }
+
+    let words = args
+        .split_off(at)
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    args.extend(expand_aliases(words, 0)?.into_iter().map(OsString::from));
+    Ok(args)
+}
+
+/// Quotes words with spaces or quotes in them so `split_words` gives them
+/// back unchanged.
+fn quote(word: &str) -> String {
+    if !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || "\"'\\".contains(c)) {
+        return word.to_string();
+    }
+    format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub fn list_aliases() -> Result<(), Box<dyn std::error::Error>> {
+    let aliases = &config().alias;
+    if aliases.is_empty() {
+        log!(
+            LogLevel::Info,
+            "No aliases yet, e.g. `artisan_cli alias add rd runner details '$1'`"
+        );
+        return Ok(());
+    }
+
+    let rows = aliases
+        .iter()
+        .map(|(name, expansion)| AliasRow {
+            name: name.clone(),
+            expansion: expansion.clone(),
+        })
+        .collect::<Vec<_>>();
+    display_table(&style_table(&mut Table::new(rows), None, false));
+    Ok(())
+}
+
+pub fn add_alias(name: &str, expansion: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let expansion = match expansion {
+        // already one quoted string, keep it as typed
+        [line] => line.clone(),
+        words => words
+            .iter()
+            .map(|word| quote(word))
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+
+    let replaced = update_config(|table| {
+        let aliases = table
+            .entry("alias")
+            .or_insert_with(|| Value::Table(TomlTable::new()));
+        match aliases {
+            Value::Table(aliases) => aliases
+                .insert(name.to_string(), Value::String(expansion.clone()))
+                .and_then(|old| old.as_str().map(str::to_string)),
+            _ => None,
+        }
+    })?;
+
+    match replaced {
+        Some(old) => log!(LogLevel::Info, "{} = {} (was {})", name, expansion, old),
+        None => log!(LogLevel::Info, "{} = {}", name, expansion),
+    }
+    Ok(())
+}
+
+pub fn remove_alias(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let removed = update_config(|table| {
+        let Some(Value::Table(aliases)) = table.get_mut("alias") else {
+            return false;
+        };
+        let removed = aliases.remove(name).is_some();
+        if aliases.is_empty() {
+            table.remove("alias");
+        }
+        removed
+    })?;
+
+    if !removed {
+        return Err(format!("No alias named {}", name).into());
+    }
+    log!(LogLevel::Info, "Removed alias {}", name);
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use artisan_middleware::{
+    dusa_collection_utils::{
+        core::{functions::current_timestamp, logger::LogLevel},
+        log,
+    },
+    timestamp::format_unix_timestamp,
+};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tabled::Table;
+use tokio::sync::OnceCell;
+
+use crate::{
+    cache::{offline, profile},
+    defs::AuditRow,
+    file::app_dir,
+    formatting::{color_status, display_table, format_relative, heading, style_table},
+    get_base_url,
+    http::send_get,
+};
+
+/// One mutating call, stored as a line of `audit.jsonl`.
+#[derive(Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: u64,
+    pub profile: String,
+    pub user_id: Option<String>,
+    // e.g. "control restart" or "discover"
+    pub command: String,
+    pub target: String,
+    pub queued_at: Option<u64>,
+    pub result: String,
+    pub errors: Vec<String>,
+}
+
+fn audit_file() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(app_dir()?.join("audit.jsonl"))
+}
+
+/// `user_id` from `account/me`, asked once per process.
+async fn user_id(client: &Client, token: &str) -> Option<String> {
+    static USER_ID: OnceCell<Option<String>> = OnceCell::const_new();
+    USER_ID
+        .get_or_init(|| async {
+            if offline() {
+                return None;
+            }
+            let request = client
+                .get(format!("{}account/me", get_base_url()))
+                .bearer_auth(token);
+            let response = send_get(request, "account/me").await.ok()?;
+            let json: serde_json::Value = response.json().await.ok()?;
+            json.get("user_id")?.as_str().map(str::to_string)
+        })
+        .await
+        .clone()
+}
+
+/// Appends a mutating call to `~/.artisan_cli/audit.jsonl`. A failed write is
+/// only warned about, it never fails the command itself.
+pub async fn audit(
+    client: &Client,
+    token: &str,
+    command: &str,
+    target: &str,
+    queued_at: Option<u64>,
+    errors: Vec<String>,
+) {
+    let entry = AuditEntry {
+        timestamp: current_timestamp(),
+        profile: profile(),
+        user_id: user_id(client, token).await,
+        command: command.to_string(),
+        target: target.to_string(),
+        queued_at,
+        result: if errors.is_empty() { "ok" } else { "failed" }.to_string(),
+        errors,
+    };
+
+    let written = audit_file().and_then(|path| {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        Ok(())
+    });
+    if let Err(err) = written {
+        log!(LogLevel::Warn, "Failed to write the audit log: {}", err);
+    }
+}
+
+/// Entries numbered from 1 in file order, so `audit show N` stays stable as
+/// the log grows.
+fn load_entries(since: u64) -> Result<Vec<(usize, AuditEntry)>, Box<dyn std::error::Error>> {
+    let path = audit_file()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let cutoff = current_timestamp().saturating_sub(since);
+    let entries = fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+        .enumerate()
+        .map(|(index, entry)| (index + 1, entry))
+        .filter(|(_, entry)| entry.timestamp >= cutoff)
+        .collect();
+
+    Ok(entries)
+}
+
+pub fn list_audit(since: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = load_entries(since)?;
+    if entries.is_empty() {
+        log!(LogLevel::Info, "No audit entries in that window");
+        return Ok(());
+    }
+
+    let rows = entries
+        .into_iter()
+        .map(|(number, entry)| AuditRow {
+            number,
+            time: format_relative(entry.timestamp),
+            profile: entry.profile,
+            user: entry.user_id.unwrap_or_else(|| "-".to_string()),
+            command: entry.command,
+            target: entry.target,
+            result: entry.result,
+        })
+        .collect::<Vec<_>>();
+
+    display_table(&style_table(&mut Table::new(rows), Some(6), false));
+    Ok(())
+}
+
+/// Every field of entry `number`, or of each entry in the window when none
+/// is given.
+pub fn show_audit(number: Option<usize>, since: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = match number {
+        Some(number) => load_entries(u64::MAX)?
+            .into_iter()
+            .filter(|(index, _)| *index == number)
+            .collect::<Vec<_>>(),
+        None => load_entries(since)?,
+    };
+    if entries.is_empty() {
+        return Err(match number {
+            Some(number) => format!("No audit entry #{}", number),
+            None => "No audit entries in that window".to_string(),
+        }
+        .into());
+    }
+
+    for (number, entry) in entries {
+        println!();
+        println!(
+            "{}",
+            heading(&format!("#{} {} {}", number, entry.command, entry.target))
+        );
+        println!(
+            "  time:      {} ({})",
+            format_unix_timestamp(entry.timestamp),
+            format_relative(entry.timestamp)
+        );
+        println!("  profile:   {}", entry.profile);
+        println!("  user:      {}", entry.user_id.as_deref().unwrap_or("-"));
+        println!("  result:    {}", color_status(&entry.result));
+        if let Some(queued_at) = entry.queued_at {
+            println!("  queued at: {}", format_unix_timestamp(queued_at));
+        }
+        for error in &entry.errors {
+            println!("  error:     {}", error);
+        }
+    }
+    println!();
+    Ok(())
+}
@@ -1,49 +1,50 @@
 use std::env;
 
 use crate::{
+    audit::audit,
     file::{get_token, save_credentials, update_env_file},
     get_base_url,
+    http::{api_client, send, send_get},
+    sdk::request_login,
 };
 use artisan_middleware::{
     api::roles::Role,
-    dusa_collection_utils::{log, core::logger::LogLevel},
+    dusa_collection_utils::{core::logger::LogLevel, log},
 };
 use owo_colors::OwoColorize;
-use reqwest::Client;
 
 pub async fn discover() -> Result<(), Box<dyn std::error::Error>> {
-    let client = Client::new();
+    let client = api_client()?;
     let token = get_token().await?;
 
-    let response = client
-        .get(&format!("{}discover", get_base_url()))
-        .bearer_auth(token)
-        .send()
-        .await?;
+    let request = client
+        .get(format!("{}discover", get_base_url()))
+        .bearer_auth(&token);
+    let response = send_get(request, "discover").await?;
 
-    if response.status().is_success() {
+    let status = response.status();
+    let mut errors = Vec::new();
+    if status.is_success() {
         log!(LogLevel::Info, "Ok !");
     } else {
-        log!(
-            LogLevel::Error,
-            "Failed to discover: {}",
-            response.text().await?
-        );
+        let text = response.text().await?;
+        log!(LogLevel::Error, "Failed to discover: {}", text);
+        errors.push(format!("{}: {}", status, text));
     }
+    audit(&client, &token, "discover", get_base_url(), None, errors).await;
 
     Ok(())
 }
 
 pub async fn whoami() -> Result<(), Box<dyn std::error::Error>> {
-    let client = Client::new();
+    let client = api_client()?;
     let token = get_token().await?;
 
     // First: get user_id
-    let response_me = client
-        .get(&format!("{}account/me", get_base_url()))
-        .bearer_auth(token.clone())
-        .send()
-        .await?;
+    let request = client
+        .get(format!("{}account/me", get_base_url()))
+        .bearer_auth(token.clone());
+    let response_me = send_get(request, "account/me").await?;
 
     let username = {
         if response_me.status().is_success() {
@@ -59,11 +60,12 @@ pub async fn whoami() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Then: get role and expiration
-    let response = client
-        .post(&format!("{}whoami", get_base_url()))
-        .bearer_auth(token)
-        .send()
-        .await?;
+    let response = send(
+        client
+            .post(format!("{}whoami", get_base_url()))
+            .bearer_auth(token),
+    )
+    .await?;
 
     if response.status().is_success() {
         let json: serde_json::Value = response.json().await?;
@@ -113,40 +115,22 @@ pub async fn whoami() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-pub async fn login(email: &String, password: &String) -> Result<(), Box<dyn std::error::Error>> {
-    let client = Client::new();
-    let response = client
-        .post(&format!("{}auth/login", get_base_url()))
-        .json(&serde_json::json!({ "email": email, "password": password }))
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        let json: serde_json::Value = response.json().await?;
-        let token = json.get("auth").and_then(|t| t.as_str());
-        let refresh = json.get("refresh").and_then(|t| t.as_str());
-
-        let (auth, refresh): (&str, &str) = match (token, refresh) {
-            (Some(token), Some(refresh)) => (token, refresh),
-            _ => {
-                log!(
-                    LogLevel::Error,
-                    "Failed to parse both refresh and auth token"
-                );
-                return Err("Login failed".into());
-            }
-        };
-
-        env::set_var("API_TOKEN", auth);
-        env::set_var("REFRESH_TOKEN", refresh);
+pub async fn login(email: &str, password: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let session = match request_login(&api_client()?, get_base_url(), email, password).await {
+        Ok(session) => session,
+        Err(err) => {
+            log!(LogLevel::Error, "{}", err);
+            return Err("Login failed".into());
+        }
+    };
 
-        update_env_file("API_TOKEN", auth)?;
-        update_env_file("REFRESH_TOKEN", refresh)?;
+    env::set_var("API_TOKEN", &session.token);
+    env::set_var("REFRESH_TOKEN", &session.refresh_token);
 
-        log!(LogLevel::Info, "Login successful, token acquired.");
-        save_credentials(&email, &password)?;
-        return Ok(());
-    }
+    update_env_file("API_TOKEN", &session.token)?;
+    update_env_file("REFRESH_TOKEN", &session.refresh_token)?;
 
-    Err("Login failed".into())
+    log!(LogLevel::Info, "Login successful, token acquired.");
+    save_credentials(email, password)?;
+    Ok(())
 }
@@ -0,0 +1,183 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use artisan_middleware::{
+    dusa_collection_utils::{
+        core::{functions::current_timestamp, logger::LogLevel},
+        log,
+    },
+    portal::ApiResponse,
+};
+use reqwest::{Client, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    config::config, file::app_dir, formatting::format_relative, get_base_url, http::send_get,
+};
+
+static NO_CACHE: AtomicBool = AtomicBool::new(false);
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// `no_cache` always goes to the API, `offline` never does and serves
+/// whatever was cached last regardless of age.
+pub fn set_cache_mode(no_cache: bool, offline: bool) {
+    NO_CACHE.store(no_cache, Ordering::Relaxed);
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+pub fn offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+pub fn no_cache() -> bool {
+    NO_CACHE.load(Ordering::Relaxed)
+}
+
+/// Sends every GET to the API until dropped, then puts the previous mode
+/// back. The mode is process wide, so later `run`/`shell` lines keep theirs.
+pub struct CacheBypass {
+    previous: bool,
+}
+
+pub fn bypass_cache() -> CacheBypass {
+    let previous = no_cache();
+    set_cache_mode(true, false);
+    CacheBypass { previous }
+}
+
+impl Drop for CacheBypass {
+    fn drop(&mut self) {
+        set_cache_mode(self.previous, false);
+    }
+}
+
+/// How long a successful response to `path` stays fresh, `None` for
+/// endpoints that must never be served from disk.
+fn ttl_for(path: &str) -> Option<u64> {
+    let ttls = &config().cache;
+    match path.split('/').next().unwrap_or_default() {
+        "nodes" | "runners" => Some(ttls.lists),
+        "node" | "runner" => Some(ttls.details),
+        "logs" => Some(ttls.logs),
+        "usage" => Some(ttls.usage),
+        // control/* queues commands, replaying one from disk would be a lie
+        _ => None,
+    }
+}
+
+pub fn profile() -> String {
+    env::var("ARTISAN_PROFILE").unwrap_or_else(|_| config().profile.clone())
+}
+
+fn cache_file(path: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = app_dir()?.join("cache").join(profile());
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("{}.json", path.replace(['/', '?', '&', '='], "_"))))
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    fetched_at: u64,
+    body: String,
+}
+
+fn read_entry(path: &str) -> Option<CacheEntry> {
+    let contents = fs::read_to_string(cache_file(path).ok()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write_entry(path: &str, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let entry = CacheEntry {
+        fetched_at: current_timestamp(),
+        body: body.to_string(),
+    };
+    fs::write(cache_file(path)?, serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+/// A GET response that may have come from disk instead of the API.
+pub struct CachedResponse {
+    status: StatusCode,
+    body: String,
+}
+
+impl CachedResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(&self.body)
+    }
+
+    pub fn text(self) -> String {
+        self.body
+    }
+}
+
+/// GETs `path` relative to the API root, answering from
+/// `~/.artisan_cli/cache/<profile>/` while the endpoint's TTL hasn't run out.
+/// Only successful responses are cached.
+pub async fn cached_get(
+    client: &Client,
+    token: &str,
+    path: &str,
+) -> Result<CachedResponse, Box<dyn std::error::Error>> {
+    let ttl = ttl_for(path);
+
+    if offline() {
+        let entry = match ttl.and_then(|_| read_entry(path)) {
+            Some(entry) => entry,
+            None => return Err(format!("No cached copy of {} to serve offline", path).into()),
+        };
+        log!(
+            LogLevel::Warn,
+            "OFFLINE, stale data: {} was cached {}",
+            path,
+            format_relative(entry.fetched_at)
+        );
+        return Ok(CachedResponse {
+            status: StatusCode::OK,
+            body: entry.body,
+        });
+    }
+
+    if let Some(ttl) = ttl.filter(|_| !NO_CACHE.load(Ordering::Relaxed)) {
+        if let Some(entry) = read_entry(path) {
+            if current_timestamp().saturating_sub(entry.fetched_at) < ttl {
+                return Ok(CachedResponse {
+                    status: StatusCode::OK,
+                    body: entry.body,
+                });
+            }
+        }
+    }
+
+    let request = client
+        .get(format!("{}{}", get_base_url(), path))
+        .bearer_auth(token);
+    let response = send_get(request, path).await?;
+    let status = response.status();
+    let body = response.text().await?;
+
+    // Written even with --no-cache so a later --offline run has something to show
+    if status.is_success() && ttl.is_some() {
+        if let Err(err) = write_entry(path, &body) {
+            log!(LogLevel::Debug, "Failed to cache {}: {}", path, err);
+        }
+    }
+
+    Ok(CachedResponse { status, body })
+}
+
+/// The payload of the last cached response to `path`, however old, without
+/// touching the API. Used for suggestions where stale beats nothing.
+pub fn last_seen<T: DeserializeOwned>(path: &str) -> Option<T> {
+    let entry = read_entry(path)?;
+    serde_json::from_str::<ApiResponse<T>>(&entry.body)
+        .ok()?
+        .data
+}
@@ -1,4 +1,12 @@
-use clap::{Parser, Subcommand};
+use std::{net::SocketAddr, path::PathBuf};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::ArgValueCandidates;
+use serde::{Deserialize, Serialize};
+
+use crate::completions::{
+    complete_instance_ids, complete_node_ids, complete_runner_ids, complete_usage_ids,
+};
 
 #[derive(Parser)]
 #[command(name = "artisan_cli")]
@@ -12,6 +20,94 @@ pub struct Cli {
     // we have 'watch -n.25 artisan_cli' at home
     #[arg(long, short, value_name = "1")]
     pub watch: Option<u64>,
+
+    // warnings and errors only
+    #[arg(long, short, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    // -v for debug logs, -vv for trace
+    #[arg(long, short, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    // method, URL, status, timing, headers and bodies of every request on stderr
+    #[arg(long, global = true)]
+    pub trace_http: bool,
+
+    // exact seconds, timestamps and byte counts instead of "3d 4h", "2m ago", "1.2 GB"
+    #[arg(long, global = true)]
+    pub raw: bool,
+
+    // flags left out fall back to ~/.artisan_cli/config.toml, see `config list`
+    #[arg(long, global = true, value_enum)]
+    pub color: Option<ColorChoice>,
+
+    #[arg(long, global = true, value_enum)]
+    pub table_style: Option<TableStyle>,
+
+    #[arg(long, global = true, value_enum)]
+    pub palette: Option<Palette>,
+
+    #[arg(long, global = true)]
+    pub no_symbols: bool,
+
+    // always ask the API, responses are still saved for --offline
+    #[arg(long, global = true, conflicts_with = "offline")]
+    pub no_cache: bool,
+
+    // serve the last cached responses without touching the network
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub connect_timeout: Option<u64>,
+
+    // retries for failed GETs, 0 turns retrying off
+    #[arg(long, global = true)]
+    pub retries: Option<u32>,
+
+    // also retry control commands and discover, which may run them twice
+    #[arg(long, global = true)]
+    pub retry_unsafe: bool,
+
+    // the flags below can also be set in the environment or config.toml
+    #[arg(long, global = true, env = "ARTISAN_PROXY", value_name = "URL")]
+    pub proxy: Option<String>,
+
+    // extra PEM bundles trusted on top of the system store
+    #[arg(
+        long,
+        global = true,
+        env = "ARTISAN_CA_BUNDLE",
+        value_delimiter = ',',
+        value_name = "FILE"
+    )]
+    pub ca_bundle: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "ARTISAN_CLIENT_CERT",
+        requires = "client_key",
+        value_name = "FILE"
+    )]
+    pub client_cert: Option<PathBuf>,
+
+    // PKCS#8 PEM key for --client-cert
+    #[arg(
+        long,
+        global = true,
+        env = "ARTISAN_CLIENT_KEY",
+        requires = "client_cert",
+        value_name = "FILE"
+    )]
+    pub client_key: Option<PathBuf>,
+
+    // skips certificate verification, for local testing only
+    #[arg(long, global = true)]
+    pub insecure: bool,
 }
 
 #[derive(Subcommand)]
@@ -24,31 +120,160 @@ pub enum TopLevelCommand {
     Instance(InstanceCmd),
     #[command(subcommand)]
     Auth(AuthCmd),
+    #[command(subcommand)]
+    Usage(UsageCmd),
+    #[command(subcommand)]
+    Metrics(MetricsCmd),
+    #[command(subcommand)]
+    Config(ConfigCmd),
+    #[command(subcommand)]
+    Audit(AuditCmd),
+    #[command(subcommand)]
+    Alias(AliasCmd),
+    Exporter {
+        #[arg(long, default_value = "127.0.0.1:9900")]
+        listen: SocketAddr,
+        #[arg(short, long, default_value = "30", value_name = "SECONDS")]
+        interval: u64,
+    },
+    WatchEvents {
+        #[arg(short, long, default_value = "30", value_name = "SECONDS")]
+        interval: u64,
+        #[arg(long, value_name = "URL")]
+        webhook: Option<String>,
+        #[arg(long)]
+        notify: bool,
+    },
+    Tree {
+        #[arg(long, value_name = "NODE_ID", add = ArgValueCandidates::new(complete_node_ids))]
+        node: Option<String>,
+        #[arg(long, value_name = "RUNNER_ID", add = ArgValueCandidates::new(complete_runner_ids))]
+        runner: Option<String>,
+        #[arg(long)]
+        json: bool,
+    },
+    Find {
+        // node ID, IP, hostname, runner name or instance ID fragment
+        term: String,
+    },
     Logs {
+        #[arg(add = ArgValueCandidates::new(complete_instance_ids))]
         instance_id: String,
         #[arg(short, long, default_value = "100")]
         lines: u64,
     },
+    // compares a fleet manifest with the runners' current state
+    Plan {
+        #[arg(short, long, value_name = "FILE")]
+        file: PathBuf,
+    },
+    // queues the control commands `plan` shows, after confirming
+    Apply {
+        #[arg(short, long, value_name = "FILE")]
+        file: PathBuf,
+        #[arg(long, short)]
+        yes: bool,
+    },
+    // runs a file of commands (stdin if omitted or `-`) in one process
+    Run {
+        #[arg(value_name = "SCRIPT")]
+        script: Option<PathBuf>,
+        #[arg(long)]
+        continue_on_error: bool,
+    },
+    // interactive prompt for these same commands, with history and ID completion
+    Shell,
+    // checks the local setup and the API connection
+    Doctor {
+        #[arg(long)]
+        json: bool,
+    },
+    // prints the registration script, e.g. `source <(artisan_cli completions bash)`
+    Completions {
+        #[arg(value_enum)]
+        shell: CompletionShell,
+    },
+}
+
+/// Shared by the list style commands, columns are referred to by their
+/// lowercased header, e.g. `uptime`, `node_id` or `data_in`.
+#[derive(Args, Default)]
+pub struct ListArgs {
+    // COLUMN=VALUE or COLUMN!=VALUE, case insensitive, repeatable
+    #[arg(long, value_name = "COLUMN=VALUE")]
+    pub filter: Vec<String>,
+    #[arg(long, value_name = "COLUMN")]
+    pub sort: Option<String>,
+    #[arg(long, requires = "sort")]
+    pub desc: bool,
+    #[arg(long, value_delimiter = ',', value_name = "COLUMN,...")]
+    pub columns: Vec<String>,
+    #[arg(long)]
+    pub limit: Option<usize>,
+    #[arg(long)]
+    pub wide: bool,
 }
 
 #[derive(Subcommand)]
 pub enum NodeCmd {
-    List,
-    Get { node_id: String },
+    List {
+        #[command(flatten)]
+        list: ListArgs,
+    },
+    Get {
+        #[arg(add = ArgValueCandidates::new(complete_node_ids))]
+        node_id: String,
+        // every section when left empty
+        #[arg(long, value_enum, value_delimiter = ',')]
+        section: Vec<NodeSection>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum NodeSection {
+    Summary,
+    Identity,
+    Status,
+    Manager,
+    Runners,
+    Warnings,
 }
 
 #[derive(Subcommand)]
 pub enum RunnerCmd {
-    List,
-    Details { runner_id: String },
-    Usage { runner_id: String },
-    Control { runner_id: String, command: String },
-    Bill { runner_id: String },
+    List {
+        #[command(flatten)]
+        list: ListArgs,
+    },
+    Details {
+        #[arg(add = ArgValueCandidates::new(complete_runner_ids))]
+        runner_id: String,
+        #[command(flatten)]
+        list: ListArgs,
+    },
+    Usage {
+        #[arg(add = ArgValueCandidates::new(complete_runner_ids))]
+        runner_id: String,
+        #[arg(long)]
+        per_instance: bool,
+    },
+    Control {
+        #[arg(add = ArgValueCandidates::new(complete_runner_ids))]
+        runner_id: String,
+        command: String,
+    },
+    Bill {
+        #[arg(add = ArgValueCandidates::new(complete_runner_ids))]
+        runner_id: String,
+    },
 }
 
 #[derive(Subcommand)]
 pub enum InstanceCmd {
-    Usage { instance_id: String },
+    Usage {
+        #[arg(add = ArgValueCandidates::new(complete_instance_ids))]
+        instance_id: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -57,3 +282,159 @@ pub enum AuthCmd {
     Discover,
     Login { email: String, password: String },
 }
+
+#[derive(Subcommand)]
+pub enum UsageCmd {
+    Compare {
+        #[arg(required = true, num_args = 2.., add = ArgValueCandidates::new(complete_usage_ids))]
+        ids: Vec<String>,
+        // compare usage/single/{id} instead of usage/group/{id}
+        #[arg(long)]
+        instances: bool,
+        #[arg(long, value_enum, default_value = "total-cpu")]
+        sort: UsageColumn,
+        #[arg(long)]
+        desc: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum UsageColumn {
+    Id,
+    TotalCpu,
+    PeakCpu,
+    AvgRam,
+    PeakRam,
+    Rx,
+    Tx,
+    Samples,
+}
+
+#[derive(Subcommand)]
+pub enum MetricsCmd {
+    Record {
+        // every runner from 'runners' when left empty
+        #[arg(add = ArgValueCandidates::new(complete_runner_ids))]
+        runner_ids: Vec<String>,
+        #[arg(short, long, default_value = "60", value_name = "SECONDS")]
+        interval: u64,
+    },
+    Show {
+        #[arg(add = ArgValueCandidates::new(complete_runner_ids))]
+        runner_id: Option<String>,
+        #[arg(long, default_value = "24h", value_parser = parse_since)]
+        since: u64,
+        #[arg(long)]
+        chart: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AuditCmd {
+    List {
+        #[arg(long, default_value = "7d", value_parser = parse_since)]
+        since: u64,
+    },
+    Show {
+        // number from `audit list`, every entry since --since when left out
+        entry: Option<usize>,
+        #[arg(long, default_value = "7d", value_parser = parse_since)]
+        since: u64,
+    },
+}
+
+/// Parses windows like `90s`, `30m`, `24h` or `7d` into seconds.
+pub fn parse_since(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => {
+            return Err(format!(
+                "unknown duration unit '{}', use s, m, h, d or w",
+                unit
+            ))
+        }
+    };
+
+    Ok(amount * multiplier)
+}
+
+// `section.key` names, e.g. `http.timeout`, `config list` shows them all
+#[derive(Subcommand)]
+pub enum ConfigCmd {
+    Get {
+        key: String,
+    },
+    Set {
+        key: String,
+        // lists like http.ca_bundle are comma separated
+        value: String,
+    },
+    Unset {
+        key: String,
+    },
+    List,
+    Edit,
+    Validate,
+}
+
+// stored in the [alias] table of config.toml, `$1`..`$9` and `$@` take the
+// arguments given after the alias
+#[derive(Subcommand)]
+pub enum AliasCmd {
+    List,
+    Add {
+        name: String,
+        // quote it to keep `$1` away from your shell, e.g. 'runner details $1'
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        expansion: Vec<String>,
+    },
+    Remove {
+        name: String,
+    },
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TableStyle {
+    Plain,
+    Rounded,
+    Markdown,
+    Ascii,
+    Compact,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Palette {
+    Default,
+    Colorblind,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
@@ -0,0 +1,118 @@
+use crate::{
+    alias::{add_alias, list_aliases, remove_alias},
+    audit::{list_audit, show_audit},
+    auth::{discover, login, whoami},
+    cli::{
+        AliasCmd, AuditCmd, AuthCmd, ConfigCmd, InstanceCmd, MetricsCmd, NodeCmd, RunnerCmd,
+        TopLevelCommand, UsageCmd,
+    },
+    completions::print_completions,
+    config::{
+        edit_config, get_config_value, list_config, set_config_value, unset_config_value,
+        validate_config,
+    },
+    doctor::doctor,
+    events::watch_events,
+    exporter::run_exporter,
+    find::find,
+    fleet::{apply, plan},
+    metrics::{record_metrics, show_metrics},
+    nodes::{get_node, list_nodes},
+    run::run_script,
+    runners::{calculate_billing, control_runner, get_runner_details, list_runners, show_logs},
+    shell::shell,
+    tree::show_tree,
+    usage::{compare_usage, get_instance_usage, get_runner_usage, get_runner_usage_per_instance},
+};
+
+/// Runs one parsed command, shared by the binary, `run` scripts and `shell`.
+pub async fn execute(command: &TopLevelCommand) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        TopLevelCommand::Node(node_cmd) => match node_cmd {
+            NodeCmd::List { list } => list_nodes(list).await?,
+            NodeCmd::Get { node_id, section } => get_node(node_id, section).await?,
+        },
+        TopLevelCommand::Runner(runner_cmd) => match runner_cmd {
+            RunnerCmd::List { list } => list_runners(list).await?,
+            RunnerCmd::Details { runner_id, list } => get_runner_details(runner_id, list).await?,
+            RunnerCmd::Usage {
+                runner_id,
+                per_instance,
+            } => {
+                if *per_instance {
+                    get_runner_usage_per_instance(runner_id).await?
+                } else {
+                    get_runner_usage(runner_id).await?
+                }
+            }
+            RunnerCmd::Control { runner_id, command } => control_runner(runner_id, command).await?,
+            RunnerCmd::Bill { runner_id } => calculate_billing(runner_id).await?,
+        },
+        TopLevelCommand::Instance(instance_cmd) => match instance_cmd {
+            InstanceCmd::Usage { instance_id } => get_instance_usage(instance_id).await?,
+        },
+        TopLevelCommand::Auth(auth_cmd) => match auth_cmd {
+            AuthCmd::Whoami => whoami().await?,
+            AuthCmd::Discover => discover().await?,
+            AuthCmd::Login { email, password } => login(email, password).await?,
+        },
+        TopLevelCommand::Usage(usage_cmd) => match usage_cmd {
+            UsageCmd::Compare {
+                ids,
+                instances,
+                sort,
+                desc,
+            } => compare_usage(ids, *instances, *sort, *desc).await?,
+        },
+        TopLevelCommand::Metrics(metrics_cmd) => match metrics_cmd {
+            MetricsCmd::Record {
+                runner_ids,
+                interval,
+            } => record_metrics(runner_ids, *interval).await?,
+            MetricsCmd::Show {
+                runner_id,
+                since,
+                chart,
+            } => show_metrics(runner_id.as_deref(), *since, *chart)?,
+        },
+        TopLevelCommand::Config(config_cmd) => match config_cmd {
+            ConfigCmd::Get { key } => get_config_value(key)?,
+            ConfigCmd::Set { key, value } => set_config_value(key, value)?,
+            ConfigCmd::Unset { key } => unset_config_value(key)?,
+            ConfigCmd::List => list_config()?,
+            ConfigCmd::Edit => edit_config()?,
+            ConfigCmd::Validate => validate_config()?,
+        },
+        TopLevelCommand::Audit(audit_cmd) => match audit_cmd {
+            AuditCmd::List { since } => list_audit(*since)?,
+            AuditCmd::Show { entry, since } => show_audit(*entry, *since)?,
+        },
+        TopLevelCommand::Alias(alias_cmd) => match alias_cmd {
+            AliasCmd::List => list_aliases()?,
+            AliasCmd::Add { name, expansion } => add_alias(name, expansion)?,
+            AliasCmd::Remove { name } => remove_alias(name)?,
+        },
+        TopLevelCommand::Exporter { listen, interval } => run_exporter(*listen, *interval).await?,
+        TopLevelCommand::WatchEvents {
+            interval,
+            webhook,
+            notify,
+        } => watch_events(*interval, webhook.as_deref(), *notify).await?,
+        TopLevelCommand::Tree { node, runner, json } => {
+            show_tree(node.as_deref(), runner.as_deref(), *json).await?
+        }
+        TopLevelCommand::Find { term } => find(term).await?,
+        TopLevelCommand::Logs { instance_id, lines } => show_logs(*lines, instance_id).await?,
+        TopLevelCommand::Plan { file } => plan(file).await?,
+        TopLevelCommand::Apply { file, yes } => apply(file, *yes).await?,
+        TopLevelCommand::Run {
+            script,
+            continue_on_error,
+        } => run_script(script.as_deref(), *continue_on_error).await?,
+        TopLevelCommand::Shell => shell().await?,
+        TopLevelCommand::Doctor { json } => doctor(*json).await?,
+        TopLevelCommand::Completions { shell } => print_completions(*shell)?,
+    }
+
+    Ok(())
+}
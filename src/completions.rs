@@ -0,0 +1,175 @@
+use std::{env, fs, io, path::PathBuf, thread, time::Duration};
+
+use artisan_middleware::{
+    dusa_collection_utils::core::functions::current_timestamp,
+    portal::{ApiResponse, NodeInfo, RunnerDetails, RunnerSummary},
+};
+use clap_complete::{
+    env::{Bash, EnvCompleter, Fish, Powershell, Zsh},
+    CompletionCandidate,
+};
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    cli::CompletionShell,
+    file::app_dir,
+    get_base_url,
+    http::{quick_client, send},
+    sdk::token_expired,
+};
+
+// Long enough that mashing tab stays local, short enough to pick up new runners
+const ID_CACHE_TTL_SECS: u64 = 120;
+// A tab press waits at most this long for fresh IDs before using the stale ones
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(2);
+const COMPLETE_VAR: &str = "COMPLETE";
+const BIN_NAME: &str = "artisan_cli";
+
+/// Runner names, node IDs and instance IDs as of `fetched_at`, stored in
+/// `completion_ids.json`.
+#[derive(Serialize, Deserialize, Default)]
+struct IdCache {
+    fetched_at: u64,
+    runners: Vec<String>,
+    nodes: Vec<String>,
+    instances: Vec<String>,
+}
+
+fn cache_file() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(app_dir()?.join("completion_ids.json"))
+}
+
+fn read_cache() -> Option<IdCache> {
+    let contents = fs::read_to_string(cache_file().ok()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// One GET, no retries and no response cache, unlike `fetch_data`.
+async fn quick_fetch<T: DeserializeOwned>(
+    client: &Client,
+    token: &str,
+    path: &str,
+) -> Result<Option<T>, Box<dyn std::error::Error>> {
+    let request = client
+        .get(format!("{}{}", get_base_url(), path))
+        .bearer_auth(token);
+    let response = send(request).await?;
+    if !response.status().is_success() {
+        return Err(format!("{} returned {}", path, response.status()).into());
+    }
+    Ok(response.json::<ApiResponse<T>>().await?.data)
+}
+
+/// Completers write to stdout, so this never refreshes the token or logs in,
+/// both of which can print. An expired session just means stale IDs.
+async fn fetch_ids() -> Result<IdCache, Box<dyn std::error::Error>> {
+    let token = env::var("API_TOKEN").unwrap_or_default();
+    if token.is_empty() || token_expired(&token) {
+        return Err("no usable session".into());
+    }
+    let client = quick_client(REFRESH_TIMEOUT)?;
+    let mut cache = IdCache {
+        fetched_at: current_timestamp(),
+        ..Default::default()
+    };
+
+    let runners = quick_fetch::<Vec<RunnerSummary>>(&client, &token, "runners")
+        .await?
+        .unwrap_or_default();
+    for runner in runners {
+        let name = runner.name.to_string();
+        if let Ok(Some(details)) =
+            quick_fetch::<Vec<RunnerDetails>>(&client, &token, &format!("runner/{}", name)).await
+        {
+            cache
+                .instances
+                .extend(details.into_iter().map(|instance| instance.id.to_string()));
+        }
+        cache.runners.push(name);
+    }
+
+    let nodes = quick_fetch::<Vec<NodeInfo>>(&client, &token, "nodes")
+        .await?
+        .unwrap_or_default();
+    cache.nodes = nodes
+        .into_iter()
+        .map(|node| node.identity.id.to_string())
+        .collect();
+
+    fs::write(cache_file()?, serde_json::to_string(&cache)?)?;
+    Ok(cache)
+}
+
+/// Returns the cached IDs, refreshing them first once they're older than
+/// `ID_CACHE_TTL_SECS`. Falls back to whatever is on disk when the API can't
+/// be reached, a stale suggestion beats none at all.
+fn cached_ids() -> IdCache {
+    let cached = match read_cache() {
+        Some(cache) if current_timestamp().saturating_sub(cache.fetched_at) < ID_CACHE_TTL_SECS => {
+            return cache
+        }
+        cached => cached,
+    };
+
+    // Completers are called synchronously from inside main's runtime, so the
+    // refresh gets a runtime of its own on a separate thread
+    let refreshed = thread::spawn(|| {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .ok()?;
+        // One deadline for every request, a slow API can't stack up timeouts
+        runtime
+            .block_on(tokio::time::timeout(REFRESH_TIMEOUT * 2, fetch_ids()))
+            .ok()?
+            .ok()
+    })
+    .join()
+    .ok()
+    .flatten();
+
+    refreshed.or(cached).unwrap_or_default()
+}
+
+fn candidates(ids: Vec<String>) -> Vec<CompletionCandidate> {
+    ids.into_iter().map(CompletionCandidate::new).collect()
+}
+
+pub fn complete_runner_ids() -> Vec<CompletionCandidate> {
+    candidates(cached_ids().runners)
+}
+
+pub fn complete_node_ids() -> Vec<CompletionCandidate> {
+    candidates(cached_ids().nodes)
+}
+
+pub fn complete_instance_ids() -> Vec<CompletionCandidate> {
+    candidates(cached_ids().instances)
+}
+
+// usage compare takes runner names or, with --instances, instance IDs
+pub fn complete_usage_ids() -> Vec<CompletionCandidate> {
+    let cache = cached_ids();
+    candidates(cache.runners.into_iter().chain(cache.instances).collect())
+}
+
+/// Prints the script that hooks `shell` up to our dynamic completions, it
+/// calls back into `COMPLETE=<shell> artisan_cli` on every tab.
+pub fn print_completions(shell: CompletionShell) -> Result<(), Box<dyn std::error::Error>> {
+    let completer: &dyn EnvCompleter = match shell {
+        CompletionShell::Bash => &Bash,
+        CompletionShell::Zsh => &Zsh,
+        CompletionShell::Fish => &Fish,
+        CompletionShell::Powershell => &Powershell,
+    };
+
+    completer.write_registration(
+        COMPLETE_VAR,
+        BIN_NAME,
+        BIN_NAME,
+        BIN_NAME,
+        &mut io::stdout(),
+    )?;
+    Ok(())
+}
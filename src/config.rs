@@ -0,0 +1,487 @@
+use std::{collections::BTreeMap, env, fs, path::PathBuf, process::Command, sync::OnceLock};
+
+use artisan_middleware::dusa_collection_utils::{core::logger::LogLevel, log};
+use clap::{CommandFactory, ValueEnum};
+use serde::{Deserialize, Serialize};
+use tabled::Table;
+use toml::{Table as TomlTable, Value};
+
+use crate::{
+    cli::{Cli, ColorChoice, Palette, TableStyle},
+    defs::ConfigRow,
+    file::{app_dir, read_env_file, remove_env_keys},
+    formatting::{display_table, style_table},
+    run::split_words,
+};
+
+/// Every key `config get/set/unset` accepts, in the order `config list` shows them.
+pub const KEYS: &[&str] = &[
+    "api_url",
+    "profile",
+    "output",
+    "color",
+    "table_style",
+    "palette",
+    "log_level",
+    "http.timeout",
+    "http.connect_timeout",
+    "http.retries",
+    "http.proxy",
+    "http.ca_bundle",
+    "http.client_cert",
+    "http.client_key",
+    "cache.lists",
+    "cache.details",
+    "cache.logs",
+    "cache.usage",
+];
+
+// .env keys that used to hold settings, and where they live now
+const LEGACY_ENV_KEYS: &[(&str, &str)] = &[
+    ("ARTISAN_API_URL", "api_url"),
+    ("ARTISAN_PROFILE", "profile"),
+    ("ARTISAN_PROXY", "http.proxy"),
+    ("ARTISAN_CA_BUNDLE", "http.ca_bundle"),
+    ("ARTISAN_CLIENT_CERT", "http.client_cert"),
+    ("ARTISAN_CLIENT_KEY", "http.client_key"),
+];
+
+// Session state that stays in .env
+const SESSION_ENV_KEYS: &[&str] = &["API_TOKEN", "REFRESH_TOKEN"];
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Table,
+    // same as --raw
+    Raw,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigLogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<ConfigLogLevel> for LogLevel {
+    fn from(level: ConfigLogLevel) -> Self {
+        match level {
+            ConfigLogLevel::Error => LogLevel::Error,
+            ConfigLogLevel::Warn => LogLevel::Warn,
+            ConfigLogLevel::Info => LogLevel::Info,
+            ConfigLogLevel::Debug => LogLevel::Debug,
+            ConfigLogLevel::Trace => LogLevel::Trace,
+        }
+    }
+}
+
+/// `~/.artisan_cli/config.toml`, command line flags and `ARTISAN_*`
+/// environment variables take precedence over it.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub api_url: String,
+    pub profile: String,
+    pub output: OutputFormat,
+    pub color: ColorChoice,
+    pub table_style: TableStyle,
+    pub palette: Palette,
+    pub log_level: ConfigLogLevel,
+    pub http: HttpConfig,
+    pub cache: CacheConfig,
+    // `[alias]` table, name to the command line it stands for, see `alias add`
+    pub alias: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub timeout: u64,
+    pub connect_timeout: u64,
+    pub retries: u32,
+    pub proxy: Option<String>,
+    pub ca_bundle: Vec<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+}
+
+/// Seconds a cached response stays fresh, see `cache::ttl_for`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    // nodes, runners
+    pub lists: u64,
+    // node/*, runner/*
+    pub details: u64,
+    pub logs: u64,
+    pub usage: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            api_url: "https://api.artisanhosting.net/v1/".to_string(),
+            profile: "default".to_string(),
+            output: OutputFormat::Table,
+            color: ColorChoice::Auto,
+            table_style: TableStyle::Plain,
+            palette: Palette::Default,
+            log_level: ConfigLogLevel::Info,
+            http: HttpConfig::default(),
+            cache: CacheConfig::default(),
+            alias: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            timeout: 30,
+            connect_timeout: 10,
+            retries: 3,
+            proxy: None,
+            ca_bundle: Vec::new(),
+            client_cert: None,
+            client_key: None,
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            lists: 15,
+            details: 10,
+            logs: 5,
+            usage: 300,
+        }
+    }
+}
+
+impl Config {
+    /// Checks the values serde can't, e.g. that files exist and URLs parse.
+    fn validate(&self) -> Result<(), String> {
+        if !self.api_url.starts_with("https://") && !self.api_url.starts_with("http://") {
+            return Err(format!("api_url: '{}' is not an http(s) URL", self.api_url));
+        }
+        if self.profile.is_empty() || self.profile.contains(['/', '\\']) {
+            return Err(format!("profile: '{}' is not a valid name", self.profile));
+        }
+        if self.http.timeout == 0 || self.http.connect_timeout == 0 {
+            return Err("http.timeout and http.connect_timeout must be above 0".to_string());
+        }
+        if let Some(proxy) = &self.http.proxy {
+            reqwest::Url::parse(proxy).map_err(|err| format!("http.proxy: {}", err))?;
+        }
+        if self.http.client_cert.is_some() != self.http.client_key.is_some() {
+            return Err("http.client_cert and http.client_key must be set together".to_string());
+        }
+
+        let files = self
+            .http
+            .ca_bundle
+            .iter()
+            .chain(&self.http.client_cert)
+            .chain(&self.http.client_key);
+        for file in files {
+            if !file.exists() {
+                return Err(format!("{} does not exist", file.display()));
+            }
+        }
+
+        let commands = Cli::command();
+        for (name, expansion) in &self.alias {
+            if name.is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+                return Err(format!("alias.{}: not a valid alias name", name));
+            }
+            // built-ins always win, an alias with the same name could never run
+            if name == "help" || commands.find_subcommand(name).is_some() {
+                return Err(format!("alias.{}: {} is a built-in command", name, name));
+            }
+            let words = split_words(expansion).map_err(|err| format!("alias.{}: {}", name, err))?;
+            if words.is_empty() {
+                return Err(format!("alias.{}: expands to nothing", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(app_dir()?.join("config.toml"))
+}
+
+fn read_table() -> Result<TomlTable, Box<dyn std::error::Error>> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(TomlTable::new());
+    }
+    let contents = fs::read_to_string(&path)?;
+    toml::from_str(&contents).map_err(|err| format!("{}: {}", path.display(), err).into())
+}
+
+fn write_table(table: &TomlTable) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(config_path()?, toml::to_string_pretty(table)?)?;
+    Ok(())
+}
+
+fn parse(table: TomlTable) -> Result<Config, String> {
+    let config: Config = Value::Table(table)
+        .try_into()
+        .map_err(|err: toml::de::Error| err.message().to_string())?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// The settings in effect, read once. A broken file is reported and ignored
+/// so `config edit` and friends can still fix it.
+pub fn config() -> &'static Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG.get_or_init(
+        || match read_table().map_err(|err| err.to_string()).and_then(parse) {
+            Ok(config) => config,
+            Err(err) => {
+                log!(
+                    LogLevel::Warn,
+                    "Ignoring config.toml ({}), run `artisan_cli config validate`",
+                    err
+                );
+                Config::default()
+            }
+        },
+    )
+}
+
+fn lookup<'a>(table: &'a TomlTable, key: &str) -> Option<&'a Value> {
+    let (section, name) = match key.split_once('.') {
+        Some((section, name)) => (table.get(section)?.as_table()?, name),
+        None => (table, key),
+    };
+    section.get(name)
+}
+
+fn check_key(key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if KEYS.contains(&key) {
+        Ok(())
+    } else {
+        Err(format!("Unknown key '{}', see `artisan_cli config list`", key).into())
+    }
+}
+
+fn render(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(items)) if items.is_empty() => "-".to_string(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| render(Some(item)))
+            .collect::<Vec<_>>()
+            .join(","),
+        Some(value) => value.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// Turns `value` into the TOML type the default for `key` has, lists are
+/// comma separated.
+fn typed_value(key: &str, value: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let defaults = TomlTable::try_from(Config::default())?;
+    Ok(match lookup(&defaults, key) {
+        Some(Value::Integer(_)) => Value::Integer(
+            value
+                .parse()
+                .map_err(|_| format!("{} expects a whole number, got '{}'", key, value))?,
+        ),
+        Some(Value::Array(_)) => Value::Array(
+            value
+                .split(',')
+                .filter(|item| !item.trim().is_empty())
+                .map(|item| Value::String(item.trim().to_string()))
+                .collect(),
+        ),
+        _ => Value::String(value.to_string()),
+    })
+}
+
+fn insert(table: &mut TomlTable, key: &str, value: Value) {
+    match key.split_once('.') {
+        Some((section, name)) => {
+            let section = table
+                .entry(section)
+                .or_insert_with(|| Value::Table(TomlTable::new()));
+            if let Value::Table(section) = section {
+                section.insert(name.to_string(), value);
+            }
+        }
+        None => {
+            table.insert(key.to_string(), value);
+        }
+    }
+}
+
+fn remove(table: &mut TomlTable, key: &str) -> bool {
+    match key.split_once('.') {
+        Some((section, name)) => {
+            let Some(Value::Table(inner)) = table.get_mut(section) else {
+                return false;
+            };
+            let removed = inner.remove(name).is_some();
+            if inner.is_empty() {
+                table.remove(section);
+            }
+            removed
+        }
+        None => table.remove(key).is_some(),
+    }
+}
+
+pub fn get_config_value(key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    check_key(key)?;
+    let effective = TomlTable::try_from(parse(read_table()?)?)?;
+    println!("{}", render(lookup(&effective, key)));
+    Ok(())
+}
+
+/// Applies `change` to config.toml, nothing is written unless the whole file
+/// is still valid afterwards.
+pub(crate) fn update_config<T>(
+    change: impl FnOnce(&mut TomlTable) -> T,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let mut table = read_table()?;
+    let changed = change(&mut table);
+    parse(table.clone()).map_err(|err| format!("Not saved, {}", err))?;
+    write_table(&table)?;
+    Ok(changed)
+}
+
+pub fn set_config_value(key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    check_key(key)?;
+    let typed = typed_value(key, value)?;
+    update_config(|table| insert(table, key, typed))?;
+    log!(LogLevel::Info, "{} = {}", key, value);
+    Ok(())
+}
+
+pub fn unset_config_value(key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    check_key(key)?;
+    let mut table = read_table()?;
+    if remove(&mut table, key) {
+        write_table(&table)?;
+        log!(LogLevel::Info, "{} reset to its default", key);
+    } else {
+        log!(LogLevel::Info, "{} was not set", key);
+    }
+    Ok(())
+}
+
+pub fn list_config() -> Result<(), Box<dyn std::error::Error>> {
+    let table = read_table()?;
+    let effective = TomlTable::try_from(parse(table.clone())?)?;
+
+    let rows = KEYS
+        .iter()
+        .map(|key| ConfigRow {
+            key: key.to_string(),
+            value: render(lookup(&effective, key)),
+            source: if lookup(&table, key).is_some() {
+                "config.toml".to_string()
+            } else {
+                "default".to_string()
+            },
+        })
+        .collect::<Vec<_>>();
+
+    display_table(&style_table(&mut Table::new(rows), None, false));
+    Ok(())
+}
+
+/// Opens config.toml in `$VISUAL` / `$EDITOR` and validates the result.
+pub fn edit_config() -> Result<(), Box<dyn std::error::Error>> {
+    let path = config_path()?;
+    if !path.exists() {
+        fs::write(
+            &path,
+            "# artisan_cli settings, `artisan_cli config list` shows every key\n",
+        )?;
+    }
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let status = Command::new(&editor).arg(&path).status()?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", editor, status).into());
+    }
+
+    validate_config()
+}
+
+/// Why config.toml can't be used, if it can't.
+pub fn config_error() -> Option<String> {
+    read_table()
+        .map_err(|err| err.to_string())
+        .and_then(parse)
+        .err()
+}
+
+pub fn validate_config() -> Result<(), Box<dyn std::error::Error>> {
+    let path = config_path()?;
+    parse(read_table()?).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    for (key, _) in read_env_file() {
+        if LEGACY_ENV_KEYS.iter().any(|(legacy, _)| *legacy == key) {
+            log!(
+                LogLevel::Warn,
+                "{} in .env overrides config.toml, remove it to use the config value",
+                key
+            );
+        } else if !SESSION_ENV_KEYS.contains(&key.as_str()) {
+            log!(LogLevel::Warn, "Unknown key {} in .env is ignored", key);
+        }
+    }
+
+    log!(LogLevel::Info, "{} is valid", path.display());
+    Ok(())
+}
+
+/// Moves settings older versions kept in `.env` into a new config.toml.
+/// Tokens stay in `.env`, nothing happens once config.toml exists.
+pub fn migrate_legacy_env() -> Result<(), Box<dyn std::error::Error>> {
+    if config_path()?.exists() {
+        return Ok(());
+    }
+
+    let mut table = TomlTable::new();
+    let mut moved = Vec::new();
+    for (key, value) in read_env_file() {
+        if let Some((_, config_key)) = LEGACY_ENV_KEYS.iter().find(|(legacy, _)| *legacy == key) {
+            insert(&mut table, config_key, typed_value(config_key, &value)?);
+            moved.push(key);
+        }
+    }
+    if moved.is_empty() {
+        return Ok(());
+    }
+
+    if let Err(err) = parse(table.clone()) {
+        log!(
+            LogLevel::Warn,
+            "Not migrating .env settings to config.toml: {}",
+            err
+        );
+        return Ok(());
+    }
+    write_table(&table)?;
+    remove_env_keys(&moved.iter().map(String::as_str).collect::<Vec<_>>())?;
+    log!(
+        LogLevel::Info,
+        "Moved {} from .env to config.toml",
+        moved.join(", ")
+    );
+    Ok(())
+}
@@ -1,15 +1,27 @@
 use tabled::Tabled;
 
-#[derive(Tabled)]
+/// A row type the shared list flags (`--filter`, `--sort`, `--columns`...) work on.
+pub trait ListRow: Tabled {
+    /// Column keys that are only shown with `--wide` or when picked with `--columns`.
+    const WIDE_COLUMNS: &'static [&'static str] = &[];
+}
+
+#[derive(Tabled, Clone)]
 pub struct RunnerRow {
     #[tabled(rename = "Name")]
     pub(crate) name: String,
     #[tabled(rename = "Status")]
     pub(crate) status: String,
-    #[tabled(rename = "Uptime (s)")]
+    #[tabled(rename = "Uptime")]
     pub(crate) uptime: String,
     #[tabled(rename = "Instances")]
     pub(crate) instances: String,
+    #[tabled(rename = "Nodes")]
+    pub(crate) nodes: String,
+}
+
+impl ListRow for RunnerRow {
+    const WIDE_COLUMNS: &'static [&'static str] = &["nodes"];
 }
 
 #[derive(Tabled)]
@@ -18,7 +30,7 @@ pub struct RunnerInstanceRow {
     pub(crate) id: String,
     #[tabled(rename = "Status")]
     pub(crate) status: String,
-    #[tabled(rename = "Uptime (s)")]
+    #[tabled(rename = "Uptime")]
     pub(crate) uptime: String,
     #[tabled(rename = "CPU Usage")]
     pub(crate) cpu: String,
@@ -30,6 +42,12 @@ pub struct RunnerInstanceRow {
     pub(crate) tx: String,
     #[tabled(rename = "Logs")]
     pub(crate) log_len: String,
+    #[tabled(rename = "Last Check")]
+    pub(crate) last_check: String,
+}
+
+impl ListRow for RunnerInstanceRow {
+    const WIDE_COLUMNS: &'static [&'static str] = &["last_check"];
 }
 
 #[derive(Tabled)]
@@ -44,6 +62,12 @@ pub struct NodeRow {
     pub(crate) runner_count: String,
     #[tabled(rename = "Last Updated")]
     pub(crate) updated: String,
+    #[tabled(rename = "Runner Names")]
+    pub(crate) runners: String,
+}
+
+impl ListRow for NodeRow {
+    const WIDE_COLUMNS: &'static [&'static str] = &["runner_names"];
 }
 
 #[derive(Tabled)]
@@ -68,6 +92,68 @@ pub struct UsageRow {
     pub samples: String,
 }
 
+#[derive(Tabled)]
+pub struct UsageCompareRow {
+    #[tabled(rename = "ID")]
+    pub id: String,
+    #[tabled(rename = "Total CPU Time")]
+    pub total_cpu: String,
+    #[tabled(rename = "Peak CPU")]
+    pub peak_cpu: String,
+    #[tabled(rename = "Avg RAM")]
+    pub avg_ram: String,
+    #[tabled(rename = "Peak RAM")]
+    pub peak_ram: String,
+    #[tabled(rename = "Data In")]
+    pub rx: String,
+    #[tabled(rename = "Data Out")]
+    pub tx: String,
+    #[tabled(rename = "Samples")]
+    pub samples: String,
+}
+
+#[derive(Tabled)]
+pub struct MetricTrendRow {
+    #[tabled(rename = "Instance ID")]
+    pub instance_id: String,
+    #[tabled(rename = "Runner")]
+    pub runner_id: String,
+    #[tabled(rename = "Status")]
+    pub status: String,
+    #[tabled(rename = "Samples")]
+    pub samples: String,
+    #[tabled(rename = "CPU Trend")]
+    pub cpu_trend: String,
+    #[tabled(rename = "Peak CPU")]
+    pub cpu_peak: String,
+    #[tabled(rename = "RAM Trend")]
+    pub ram_trend: String,
+    #[tabled(rename = "Peak RAM")]
+    pub ram_peak: String,
+    #[tabled(rename = "Data In")]
+    pub rx: String,
+    #[tabled(rename = "Data Out")]
+    pub tx: String,
+}
+
+#[derive(Tabled)]
+pub struct FindRow {
+    #[tabled(rename = "Type")]
+    pub kind: String,
+    #[tabled(rename = "ID")]
+    pub id: String,
+    #[tabled(rename = "Status")]
+    pub status: String,
+    #[tabled(rename = "Matched")]
+    pub field: String,
+    #[tabled(rename = "Value")]
+    pub value: String,
+    #[tabled(rename = "Location")]
+    pub location: String,
+    #[tabled(rename = "Inspect With")]
+    pub command: String,
+}
+
 #[derive(Tabled)]
 pub struct BillingEntry {
     #[tabled(rename = "Cost Type")]
@@ -84,6 +170,8 @@ pub struct GenericRow {
     pub value: String,
 }
 
+impl ListRow for GenericRow {}
+
 #[derive(Tabled)]
 pub struct NodeSummaryRow {
     #[tabled(rename = "Node ID")]
@@ -103,3 +191,77 @@ pub struct NodeSummaryRow {
     #[tabled(rename = "Last Updated")]
     pub last_updated: String,
 }
+
+#[derive(Tabled)]
+pub struct ConfigRow {
+    #[tabled(rename = "Key")]
+    pub(crate) key: String,
+    #[tabled(rename = "Value")]
+    pub(crate) value: String,
+    #[tabled(rename = "Source")]
+    pub(crate) source: String,
+}
+
+#[derive(Tabled)]
+pub struct DoctorRow {
+    #[tabled(rename = "Check")]
+    pub(crate) check: String,
+    #[tabled(rename = "Status")]
+    pub(crate) status: String,
+    #[tabled(rename = "Detail")]
+    pub(crate) detail: String,
+}
+
+#[derive(Tabled)]
+pub struct PlanRow {
+    #[tabled(rename = "Runner")]
+    pub(crate) runner: String,
+    #[tabled(rename = "Status")]
+    pub(crate) status: String,
+    #[tabled(rename = "Instances")]
+    pub(crate) instances: String,
+    #[tabled(rename = "Desired")]
+    pub(crate) desired: String,
+    #[tabled(rename = "Action")]
+    pub(crate) action: String,
+}
+
+#[derive(Tabled)]
+pub struct AuditRow {
+    #[tabled(rename = "#")]
+    pub(crate) number: usize,
+    #[tabled(rename = "Time")]
+    pub(crate) time: String,
+    #[tabled(rename = "Profile")]
+    pub(crate) profile: String,
+    #[tabled(rename = "User")]
+    pub(crate) user: String,
+    #[tabled(rename = "Command")]
+    pub(crate) command: String,
+    #[tabled(rename = "Target")]
+    pub(crate) target: String,
+    #[tabled(rename = "Result")]
+    pub(crate) result: String,
+}
+
+#[derive(Tabled)]
+pub struct RunRow {
+    #[tabled(rename = "Line")]
+    pub(crate) line: usize,
+    #[tabled(rename = "Command")]
+    pub(crate) command: String,
+    #[tabled(rename = "Result")]
+    pub(crate) result: String,
+    #[tabled(rename = "Time")]
+    pub(crate) time: String,
+    #[tabled(rename = "Error")]
+    pub(crate) error: String,
+}
+
+#[derive(Tabled)]
+pub struct AliasRow {
+    #[tabled(rename = "Alias")]
+    pub(crate) name: String,
+    #[tabled(rename = "Expands To")]
+    pub(crate) expansion: String,
+}
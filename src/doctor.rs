@@ -0,0 +1,363 @@
+use std::{
+    env, fs,
+    time::{Instant, SystemTime},
+};
+
+use artisan_middleware::dusa_collection_utils::core::functions::current_timestamp;
+use reqwest::{header::DATE, StatusCode};
+use serde::Serialize;
+use tabled::Table;
+
+use crate::{
+    audit::audit,
+    config::{config_error, config_path},
+    defs::DoctorRow,
+    file::{app_dir, load_credentials},
+    formatting::{display_table, format_duration, heading, style_table},
+    get_base_url,
+    http::{api_client, send, send_get},
+    sdk::token_expiry,
+};
+
+// Past these the server and this machine disagree enough to matter for token expiry
+const SKEW_WARN_SECS: i64 = 30;
+const SKEW_FAIL_SECS: i64 = 300;
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Outcome {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl Outcome {
+    fn label(self) -> &'static str {
+        match self {
+            Outcome::Ok => "ok",
+            Outcome::Warning => "warning",
+            Outcome::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Check {
+    name: &'static str,
+    status: Outcome,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: String) -> Check {
+        Check {
+            name,
+            status: Outcome::Ok,
+            detail,
+            hint: None,
+        }
+    }
+
+    fn warning(name: &'static str, detail: String, hint: &str) -> Check {
+        Check {
+            name,
+            status: Outcome::Warning,
+            detail,
+            hint: Some(hint.to_string()),
+        }
+    }
+
+    fn failed(name: &'static str, detail: String, hint: &str) -> Check {
+        Check {
+            name,
+            status: Outcome::Failed,
+            detail,
+            hint: Some(hint.to_string()),
+        }
+    }
+}
+
+fn check_env_file() -> Check {
+    const NAME: &str = ".env";
+    let path = match app_dir() {
+        Ok(dir) => dir.join(".env"),
+        Err(err) => return Check::failed(NAME, err.to_string(), "make sure $HOME is set"),
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) => {
+            return Check::failed(
+                NAME,
+                format!("{}: {}", path.display(), err),
+                "run `artisan_cli auth login` to create it",
+            )
+        }
+    };
+
+    let entries = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+    let mut keys = 0;
+    for (number, line) in entries {
+        if !line.contains('=') {
+            return Check::failed(
+                NAME,
+                format!("line {} is not KEY=VALUE", number + 1),
+                "fix or delete that line",
+            );
+        }
+        keys += 1;
+    }
+    Check::ok(NAME, format!("{}, {} keys", path.display(), keys))
+}
+
+fn check_config() -> Check {
+    const NAME: &str = "config.toml";
+    match (config_error(), config_path()) {
+        (Some(err), _) => Check::failed(NAME, err, "run `artisan_cli config validate`"),
+        (None, Ok(path)) if path.exists() => Check::ok(NAME, "valid".to_string()),
+        _ => Check::ok(NAME, "not present, using defaults".to_string()),
+    }
+}
+
+fn check_token() -> Check {
+    const NAME: &str = "API token";
+    let token = env::var("API_TOKEN").unwrap_or_default();
+    if token.is_empty() {
+        return Check::failed(
+            NAME,
+            "API_TOKEN is not set".to_string(),
+            "run `artisan_cli auth login`",
+        );
+    }
+    let Some(exp) = token_expiry(&token) else {
+        return Check::failed(
+            NAME,
+            "not a well-formed JWT".to_string(),
+            "run `artisan_cli auth login` to replace it",
+        );
+    };
+
+    let now = current_timestamp();
+    if exp > now {
+        return Check::ok(NAME, format!("expires in {}", format_duration(exp - now)));
+    }
+    let expired = format!("expired {} ago", format_duration(now - exp));
+    if env::var("REFRESH_TOKEN").is_ok_and(|token| !token.is_empty()) {
+        Check::warning(
+            NAME,
+            expired,
+            "it's refreshed on the next call, `artisan_cli auth login` if that fails",
+        )
+    } else {
+        Check::failed(
+            NAME,
+            format!("{}, no REFRESH_TOKEN", expired),
+            "run `artisan_cli auth login`",
+        )
+    }
+}
+
+fn check_credentials() -> Check {
+    const NAME: &str = "credentials.ejson";
+    let exists = app_dir().is_ok_and(|dir| dir.join("credentials.ejson").exists());
+    if !exists {
+        return Check::warning(
+            NAME,
+            "not saved, an expired session can't log back in by itself".to_string(),
+            "run `artisan_cli auth login`",
+        );
+    }
+    match load_credentials() {
+        Ok((email, _)) if !email.is_empty() => Check::ok(NAME, format!("decrypts, {}", email)),
+        Ok(_) => Check::failed(
+            NAME,
+            "decrypts but has no email".to_string(),
+            "run `artisan_cli auth login` to save them again",
+        ),
+        Err(err) => Check::failed(
+            NAME,
+            format!("doesn't decrypt: {}", err),
+            "delete ~/.artisan_cli/credentials.ejson and run `artisan_cli auth login`",
+        ),
+    }
+}
+
+/// Reachability plus clock skew from the response's `Date` header.
+async fn check_api() -> (Check, Check) {
+    const NAME: &str = "API reachable";
+    const SKEW: &str = "Clock skew";
+    let url = get_base_url();
+
+    let started = Instant::now();
+    let response = match api_client() {
+        Ok(client) => send(client.get(url)).await.map_err(|err| err.to_string()),
+        Err(err) => Err(err.to_string()),
+    };
+    let response = match response {
+        Ok(response) => response,
+        Err(err) => {
+            return (
+                Check::failed(
+                    NAME,
+                    format!("{}: {}", url, err),
+                    "check your network, http.proxy and api_url / ARTISAN_API_URL",
+                ),
+                Check::warning(
+                    SKEW,
+                    "skipped, API unreachable".to_string(),
+                    "fix the API check first",
+                ),
+            )
+        }
+    };
+
+    let reachable = Check::ok(
+        NAME,
+        format!(
+            "{} answered {} in {}ms",
+            url,
+            response.status(),
+            started.elapsed().as_millis()
+        ),
+    );
+
+    let server_time = response
+        .headers()
+        .get(DATE)
+        .and_then(|date| date.to_str().ok())
+        .and_then(|date| httpdate::parse_http_date(date).ok());
+    let Some(server_time) = server_time else {
+        return (
+            reachable,
+            Check::warning(
+                SKEW,
+                "the API sent no Date header".to_string(),
+                "nothing to do",
+            ),
+        );
+    };
+
+    let skew = match SystemTime::now().duration_since(server_time) {
+        Ok(ahead) => ahead.as_secs() as i64,
+        Err(behind) => -(behind.duration().as_secs() as i64),
+    };
+    let detail = match skew {
+        0 => "in sync with the API".to_string(),
+        ahead if ahead > 0 => format!("{}s ahead of the API", ahead),
+        behind => format!("{}s behind the API", -behind),
+    };
+    let skew = match skew.abs() {
+        secs if secs <= SKEW_WARN_SECS => Check::ok(SKEW, detail),
+        secs if secs <= SKEW_FAIL_SECS => Check::warning(
+            SKEW,
+            detail,
+            "sync your clock (NTP), token expiry checks use it",
+        ),
+        _ => Check::failed(
+            SKEW,
+            detail,
+            "sync your clock (NTP), tokens will look expired or valid when they aren't",
+        ),
+    };
+    (reachable, skew)
+}
+
+async fn check_discover() -> Check {
+    const NAME: &str = "discover";
+    let token = env::var("API_TOKEN").unwrap_or_default();
+    let client = match api_client() {
+        Ok(client) => client,
+        Err(err) => return Check::failed(NAME, err.to_string(), "check the TLS settings"),
+    };
+
+    let request = client
+        .get(format!("{}discover", get_base_url()))
+        .bearer_auth(&token);
+    let response = match send_get(request, "discover").await {
+        Ok(response) => response,
+        Err(err) => return Check::failed(NAME, err.to_string(), "fix the API check first"),
+    };
+
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let body = body.trim().chars().take(120).collect::<String>();
+
+    // discover touches server state, so it's audited like `auth discover`
+    let errors = if status.is_success() {
+        Vec::new()
+    } else {
+        vec![format!("{}: {}", status, body)]
+    };
+    audit(&client, &token, "discover", get_base_url(), None, errors).await;
+
+    match status {
+        status if status.is_success() => Check::ok(NAME, format!("{} {}", status, body)),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Check::failed(
+            NAME,
+            format!("{} {}", status, body),
+            "the token was rejected, run `artisan_cli auth login`",
+        ),
+        _ => Check::failed(
+            NAME,
+            format!("{} {}", status, body),
+            "the API is up but unhappy, try again or report it",
+        ),
+    }
+}
+
+/// Runs every check and prints a report, errors if any of them failed so
+/// scripts can rely on the exit code.
+pub async fn doctor(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let (reachable, skew) = check_api().await;
+    let discover = if reachable.status == Outcome::Failed {
+        Check::warning(
+            "discover",
+            "skipped, API unreachable".to_string(),
+            "fix the API check first",
+        )
+    } else {
+        check_discover().await
+    };
+    let checks = vec![
+        check_env_file(),
+        check_config(),
+        check_token(),
+        check_credentials(),
+        reachable,
+        discover,
+        skew,
+    ];
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+    } else {
+        let rows = checks
+            .iter()
+            .map(|check| DoctorRow {
+                check: check.name.to_string(),
+                status: check.status.label().to_string(),
+                detail: check.detail.clone(),
+            })
+            .collect::<Vec<_>>();
+        display_table(&style_table(&mut Table::new(rows), Some(1), false));
+
+        for check in checks.iter().filter(|check| check.status != Outcome::Ok) {
+            if let Some(hint) = &check.hint {
+                println!("{} {}", heading(&format!("{}:", check.name)), hint);
+            }
+        }
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == Outcome::Failed)
+        .count();
+    if failed > 0 {
+        return Err(format!("{} of {} checks failed", failed, checks.len()).into());
+    }
+    Ok(())
+}
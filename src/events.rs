@@ -0,0 +1,280 @@
+use std::{collections::BTreeMap, process::Command, time::Duration};
+
+use artisan_middleware::{
+    dusa_collection_utils::{
+        core::{functions::current_timestamp, logger::LogLevel},
+        log,
+    },
+    portal::{NodeDetails, NodeInfo, RunnerSummary},
+};
+use reqwest::Client;
+use serde::Serialize;
+use tokio::time::sleep;
+
+use crate::{
+    cache::{bypass_cache, offline},
+    fetch_data,
+    file::get_token,
+    formatting::strip_ansi_codes,
+    http::{api_client, send},
+};
+
+#[derive(Serialize)]
+pub struct FleetEvent {
+    pub timestamp: u64,
+    pub kind: &'static str,
+    pub target: String,
+    pub previous: String,
+    pub current: String,
+    pub message: String,
+}
+
+/// Statuses and warning counts keyed by runner name / node ID.
+#[derive(Default)]
+struct Snapshot {
+    runners: BTreeMap<String, String>,
+    nodes: BTreeMap<String, String>,
+    warnings: BTreeMap<String, u32>,
+}
+
+async fn take_snapshot(client: &Client) -> Result<Snapshot, Box<dyn std::error::Error>> {
+    let token = get_token().await?;
+    let mut snapshot = Snapshot::default();
+
+    let runners = fetch_data::<Vec<RunnerSummary>>(client, &token, "runners").await?;
+    for runner in runners.unwrap_or_default() {
+        snapshot.runners.insert(
+            runner.name.to_string(),
+            strip_ansi_codes(&runner.status.to_string()),
+        );
+    }
+
+    let nodes = fetch_data::<Vec<NodeInfo>>(client, &token, "nodes").await?;
+    for node in nodes.unwrap_or_default() {
+        let id = node.identity.id.to_string();
+        snapshot
+            .nodes
+            .insert(id.clone(), strip_ansi_codes(&node.status.to_string()));
+
+        match fetch_data::<NodeDetails>(client, &token, &format!("node/{}", id)).await {
+            Ok(Some(details)) => {
+                snapshot.warnings.insert(id, details.manager_data.warning);
+            }
+            Ok(None) => {}
+            Err(err) => log!(LogLevel::Warn, "Failed to get warnings for {}: {}", id, err),
+        }
+    }
+
+    Ok(snapshot)
+}
+
+fn event(
+    kind: &'static str,
+    target: &str,
+    previous: &str,
+    current: &str,
+    message: String,
+) -> FleetEvent {
+    FleetEvent {
+        timestamp: current_timestamp(),
+        kind,
+        target: target.to_string(),
+        previous: previous.to_string(),
+        current: current.to_string(),
+        message,
+    }
+}
+
+fn diff_snapshots(old: &Snapshot, new: &Snapshot) -> Vec<FleetEvent> {
+    let mut events = Vec::new();
+    let gone = "Missing";
+
+    for (name, status) in &new.runners {
+        match old.runners.get(name) {
+            Some(previous) if previous == status => {}
+            Some(previous) => {
+                let kind = if status == "Stopped" {
+                    "runner_stopped"
+                } else {
+                    "runner_status_changed"
+                };
+                events.push(event(
+                    kind,
+                    name,
+                    previous,
+                    status,
+                    format!("Runner {} went from {} to {}", name, previous, status),
+                ));
+            }
+            None => events.push(event(
+                "runner_added",
+                name,
+                gone,
+                status,
+                format!("Runner {} appeared as {}", name, status),
+            )),
+        }
+    }
+    for (name, previous) in &old.runners {
+        if !new.runners.contains_key(name) {
+            events.push(event(
+                "runner_removed",
+                name,
+                previous,
+                gone,
+                format!("Runner {} is no longer listed", name),
+            ));
+        }
+    }
+
+    for (id, status) in &new.nodes {
+        match old.nodes.get(id) {
+            Some(previous) if previous == status => {}
+            Some(previous) => {
+                // Warning, Idle and the like still report in, only these mean it's down
+                let kind = if matches!(status.as_str(), "Stopped" | "Unknown") {
+                    "node_offline"
+                } else {
+                    "node_status_changed"
+                };
+                events.push(event(
+                    kind,
+                    id,
+                    previous,
+                    status,
+                    format!("Node {} went from {} to {}", id, previous, status),
+                ));
+            }
+            None => events.push(event(
+                "node_added",
+                id,
+                gone,
+                status,
+                format!("Node {} appeared as {}", id, status),
+            )),
+        }
+    }
+    for (id, previous) in &old.nodes {
+        if !new.nodes.contains_key(id) {
+            events.push(event(
+                "node_offline",
+                id,
+                previous,
+                gone,
+                format!("Node {} is no longer reporting", id),
+            ));
+        }
+    }
+
+    for (id, count) in &new.warnings {
+        if let Some(previous) = old.warnings.get(id) {
+            if count > previous {
+                events.push(event(
+                    "warnings_increased",
+                    id,
+                    &previous.to_string(),
+                    &count.to_string(),
+                    format!("Node {} warnings went from {} to {}", id, previous, count),
+                ));
+            }
+        }
+    }
+
+    events
+}
+
+fn desktop_notify(event: &FleetEvent) {
+    let result = if cfg!(target_os = "macos") {
+        Command::new("osascript")
+            .arg("-e")
+            .arg(format!(
+                "display notification \"{}\" with title \"Artisan Hosting\"",
+                event.message.replace('"', "'")
+            ))
+            .status()
+    } else if cfg!(target_os = "linux") {
+        Command::new("notify-send")
+            .arg("Artisan Hosting")
+            .arg(&event.message)
+            .status()
+    } else {
+        log!(
+            LogLevel::Debug,
+            "Desktop notifications aren't supported on this platform"
+        );
+        return;
+    };
+
+    if let Err(err) = result {
+        log!(
+            LogLevel::Warn,
+            "Failed to send desktop notification: {}",
+            err
+        );
+    }
+}
+
+/// Polls runner and node status every `interval` seconds and emits an event
+/// for every change as a JSON line, to `webhook` and optionally the desktop.
+pub async fn watch_events(
+    interval: u64,
+    webhook: Option<&str>,
+    notify: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if interval < 10 {
+        log!(
+            LogLevel::Error,
+            "Polling more often than every 10 seconds isn't supported"
+        );
+        return Ok(());
+    }
+    if offline() {
+        return Err("watch-events needs the API, drop --offline".into());
+    }
+    // A cached snapshot would just repeat the last one
+    let _live = bypass_cache();
+
+    let client = api_client()?;
+    let mut previous = take_snapshot(&client).await?;
+    log!(
+        LogLevel::Info,
+        "Watching {} runners and {} nodes every {}s",
+        previous.runners.len(),
+        previous.nodes.len(),
+        interval
+    );
+
+    loop {
+        sleep(Duration::from_secs(interval)).await;
+
+        let current = match take_snapshot(&client).await {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                log!(LogLevel::Error, "Failed to poll fleet status: {}", err);
+                continue;
+            }
+        };
+
+        for event in diff_snapshots(&previous, &current) {
+            println!("{}", serde_json::to_string(&event)?);
+
+            if let Some(url) = webhook {
+                match send(client.post(url).json(&event)).await {
+                    Ok(response) if !response.status().is_success() => log!(
+                        LogLevel::Warn,
+                        "Webhook rejected event: {}",
+                        response.status()
+                    ),
+                    Ok(_) => {}
+                    Err(err) => log!(LogLevel::Warn, "Failed to deliver webhook: {}", err),
+                }
+            }
+
+            if notify {
+                desktop_notify(&event);
+            }
+        }
+
+        previous = current;
+    }
+}
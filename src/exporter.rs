@@ -0,0 +1,356 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use artisan_middleware::{
+    dusa_collection_utils::{
+        core::{functions::current_timestamp, logger::LogLevel},
+        log,
+    },
+    portal::{NodeInfo, RunnerDetails, RunnerSummary},
+};
+use reqwest::Client;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::RwLock,
+    time::sleep,
+};
+
+use crate::{
+    cache::{bypass_cache, offline},
+    fetch_data,
+    file::get_token,
+    formatting::strip_ansi_codes,
+    http::api_client,
+    metrics::parse_leading_number,
+    usage::request_usage,
+};
+
+/// Collects gauges grouped by metric family so HELP/TYPE are written once.
+#[derive(Default)]
+struct Gauges {
+    families: Vec<(&'static str, &'static str, Vec<String>)>,
+}
+
+impl Gauges {
+    fn set(&mut self, name: &'static str, help: &'static str, labels: &[(&str, &str)], value: f64) {
+        let labels = labels
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+            .collect::<Vec<_>>()
+            .join(",");
+        let sample = if labels.is_empty() {
+            format!("{} {}", name, value)
+        } else {
+            format!("{}{{{}}} {}", name, labels, value)
+        };
+
+        match self.families.iter_mut().find(|family| family.0 == name) {
+            Some(family) => family.2.push(sample),
+            None => self.families.push((name, help, vec![sample])),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, help, samples) in &self.families {
+            out.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} gauge\n",
+                name, help, name
+            ));
+            for sample in samples {
+                out.push_str(sample);
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn is_running(status: &str) -> f64 {
+    if status == "Running" {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+async fn collect(client: &Client, gauges: &mut Gauges) -> usize {
+    let mut errors = 0;
+    let token = match get_token().await {
+        Ok(token) => token,
+        Err(err) => {
+            log!(LogLevel::Error, "Exporter failed to get a token: {}", err);
+            return 1;
+        }
+    };
+
+    match fetch_data::<Vec<NodeInfo>>(client, &token, "nodes").await {
+        Ok(nodes) => {
+            for node in nodes.unwrap_or_default() {
+                let id = node.identity.id.to_string();
+                let status = strip_ansi_codes(&node.status.to_string());
+                let ip = node.ip_address.to_string();
+                let labels = [("node", id.as_str())];
+
+                gauges.set(
+                    "artisan_node_up",
+                    "Whether the node reports Running",
+                    &[("node", &id), ("ip", &ip), ("status", &status)],
+                    is_running(&status),
+                );
+                gauges.set(
+                    "artisan_node_runners",
+                    "Runners hosted on the node",
+                    &labels,
+                    node.runners.len() as f64,
+                );
+                if let Ok(updated) = node.last_updated.to_string().parse::<f64>() {
+                    gauges.set(
+                        "artisan_node_last_updated_seconds",
+                        "Unix time the node last reported in",
+                        &labels,
+                        updated,
+                    );
+                }
+            }
+        }
+        Err(err) => {
+            log!(LogLevel::Error, "Exporter failed to poll nodes: {}", err);
+            errors += 1;
+        }
+    }
+
+    let runners = match fetch_data::<Vec<RunnerSummary>>(client, &token, "runners").await {
+        Ok(runners) => runners.unwrap_or_default(),
+        Err(err) => {
+            log!(LogLevel::Error, "Exporter failed to poll runners: {}", err);
+            return errors + 1;
+        }
+    };
+
+    for runner in runners {
+        let name = runner.name.to_string();
+        let status = strip_ansi_codes(&runner.status.to_string());
+        let labels = [("runner", name.as_str())];
+
+        gauges.set(
+            "artisan_runner_up",
+            "Whether the runner group reports Running",
+            &[("runner", &name), ("status", &status)],
+            is_running(&status),
+        );
+        gauges.set(
+            "artisan_runner_uptime_seconds",
+            "Runner group uptime",
+            &labels,
+            runner.uptime.unwrap_or(0) as f64,
+        );
+
+        match fetch_data::<Vec<RunnerDetails>>(client, &token, &format!("runner/{}", name)).await {
+            Ok(instances) => {
+                let instances = instances.unwrap_or_default();
+                gauges.set(
+                    "artisan_runner_instances",
+                    "Instances in the runner group",
+                    &labels,
+                    instances.len() as f64,
+                );
+
+                for instance in instances {
+                    let id = instance.id.to_string();
+                    let status = strip_ansi_codes(&instance.status.to_string());
+                    let labels = [("runner", name.as_str()), ("instance", id.as_str())];
+
+                    gauges.set(
+                        "artisan_instance_up",
+                        "Whether the instance reports Running",
+                        &[("runner", &name), ("instance", &id), ("status", &status)],
+                        is_running(&status),
+                    );
+
+                    let Some(health) = instance.health else {
+                        continue;
+                    };
+                    if let Some(cpu) = parse_leading_number(&health.cpu_usage.to_string()) {
+                        gauges.set("artisan_instance_cpu", "Instance CPU usage", &labels, cpu);
+                    }
+                    if let Some(ram) = parse_leading_number(&health.ram_usage.to_string()) {
+                        gauges.set("artisan_instance_ram", "Instance RAM usage", &labels, ram);
+                    }
+                    gauges.set(
+                        "artisan_instance_rx_bytes",
+                        "Bytes received by the instance",
+                        &labels,
+                        health.rx_bytes as f64,
+                    );
+                    gauges.set(
+                        "artisan_instance_tx_bytes",
+                        "Bytes sent by the instance",
+                        &labels,
+                        health.tx_bytes as f64,
+                    );
+                    gauges.set(
+                        "artisan_instance_uptime_seconds",
+                        "Instance uptime",
+                        &labels,
+                        health.uptime as f64,
+                    );
+                }
+            }
+            Err(err) => {
+                log!(LogLevel::Error, "Exporter failed to poll {}: {}", name, err);
+                errors += 1;
+            }
+        }
+
+        match request_usage(client, &token, &format!("usage/group/{}", name)).await {
+            Ok(Some(summary)) => {
+                let usage = [
+                    (
+                        "artisan_usage_total_cpu",
+                        "Billed CPU time",
+                        f64::from(summary.total_cpu),
+                    ),
+                    (
+                        "artisan_usage_peak_cpu",
+                        "Billed peak CPU",
+                        f64::from(summary.peak_cpu),
+                    ),
+                    (
+                        "artisan_usage_avg_ram_mb",
+                        "Billed average RAM",
+                        summary.avg_memory,
+                    ),
+                    (
+                        "artisan_usage_peak_ram_mb",
+                        "Billed peak RAM",
+                        summary.peak_memory,
+                    ),
+                    (
+                        "artisan_usage_rx_bytes",
+                        "Billed bytes in",
+                        summary.total_rx as f64,
+                    ),
+                    (
+                        "artisan_usage_tx_bytes",
+                        "Billed bytes out",
+                        summary.total_tx as f64,
+                    ),
+                    (
+                        "artisan_usage_samples",
+                        "Samples in the billing window",
+                        summary.total_samples as f64,
+                    ),
+                ];
+                for (metric, help, value) in usage {
+                    gauges.set(metric, help, &labels, value);
+                }
+            }
+            Ok(None) => {}
+            Err(err) => {
+                log!(
+                    LogLevel::Error,
+                    "Exporter failed to poll usage of {}: {}",
+                    name,
+                    err
+                );
+                errors += 1;
+            }
+        }
+    }
+
+    errors
+}
+
+async fn serve(listener: TcpListener, state: Arc<RwLock<String>>) {
+    loop {
+        let (mut socket, _) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(err) => {
+                log!(LogLevel::Error, "Exporter failed to accept: {}", err);
+                continue;
+            }
+        };
+
+        let body = state.read().await.clone();
+        tokio::spawn(async move {
+            let mut buffer = [0u8; 1024];
+            let read = socket.read(&mut buffer).await.unwrap_or(0);
+            let request = String::from_utf8_lossy(&buffer[..read]);
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+            let (status, body) = if path == "/metrics" {
+                ("200 OK", body)
+            } else {
+                ("404 Not Found", "Not found, try /metrics\n".to_string())
+            };
+
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+/// Serves Prometheus gauges on `/metrics`. The portal is polled every
+/// `interval` seconds, scrapes always get the latest poll.
+pub async fn run_exporter(
+    listen: SocketAddr,
+    interval: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if interval < 10 {
+        log!(
+            LogLevel::Error,
+            "Polling more often than every 10 seconds isn't supported"
+        );
+        return Ok(());
+    }
+    if offline() {
+        return Err("The exporter needs the API, drop --offline".into());
+    }
+    // Gauges should be as fresh as the last poll, not the cache TTL
+    let _live = bypass_cache();
+
+    let listener = TcpListener::bind(listen).await?;
+    let state = Arc::new(RwLock::new(String::new()));
+    tokio::spawn(serve(listener, state.clone()));
+    log!(
+        LogLevel::Info,
+        "Serving metrics on http://{}/metrics, polling every {}s",
+        listen,
+        interval
+    );
+
+    let client = api_client()?;
+    loop {
+        let mut gauges = Gauges::default();
+        let errors = collect(&client, &mut gauges).await;
+
+        gauges.set(
+            "artisan_exporter_poll_errors",
+            "Requests that failed during the last poll",
+            &[],
+            errors as f64,
+        );
+        gauges.set(
+            "artisan_exporter_last_poll_seconds",
+            "Unix time of the last poll",
+            &[],
+            current_timestamp() as f64,
+        );
+
+        *state.write().await = gauges.render();
+        sleep(Duration::from_secs(interval)).await;
+    }
+}
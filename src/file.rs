@@ -1,15 +1,24 @@
 use std::fs::create_dir_all;
+use std::path::PathBuf;
 use std::{env, fs};
 
-use artisan_middleware::dusa_collection_utils::log;
 use artisan_middleware::dusa_collection_utils::core::logger::LogLevel;
+use artisan_middleware::dusa_collection_utils::log;
 use artisan_middleware::encryption::{simple_decrypt, simple_encrypt};
-use artisan_middleware::timestamp::current_timestamp;
-use reqwest::Client;
-use serde_json::json;
 
 use crate::auth::login;
+use crate::cache::offline;
 use crate::get_base_url;
+use crate::http::api_client;
+use crate::sdk::{request_refresh, token_expired, Session};
+
+/// Returns `~/.artisan_cli`, creating it if needed.
+pub fn app_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let home_dir = dirs::home_dir().ok_or("Failed to get home directory")?;
+    let dir = home_dir.join(".artisan_cli");
+    create_dir_all(&dir)?;
+    Ok(dir)
+}
 
 pub fn save_credentials(email: &str, password: &str) -> Result<(), Box<dyn std::error::Error>> {
     let home_dir = dirs::home_dir().ok_or("Failed to get home directory")?;
@@ -26,7 +35,7 @@ pub fn save_credentials(email: &str, password: &str) -> Result<(), Box<dyn std::
     Ok(())
 }
 
-fn load_credentials() -> Result<(String, String), Box<dyn std::error::Error>> {
+pub fn load_credentials() -> Result<(String, String), Box<dyn std::error::Error>> {
     let home_dir = dirs::home_dir().ok_or("Failed to get home directory")?;
     let mut credentials_file = home_dir.join(".artisan_cli");
     credentials_file = credentials_file.join("credentials.ejson");
@@ -54,51 +63,37 @@ fn load_credentials() -> Result<(String, String), Box<dyn std::error::Error>> {
 
 pub async fn get_token() -> Result<String, Box<dyn std::error::Error>> {
     let token = env::var("API_TOKEN").unwrap_or_default();
+    // Nothing is sent offline, so there's no point refreshing or logging in
+    if offline() {
+        return Ok(token);
+    }
     if token.is_empty() {
         log!(LogLevel::Warn, "Token not found, please log in.");
         return Err("Token not found.".into());
     }
 
-    // Decode the token to check expiration
-    let token_data: Vec<&str> = token.split('.').collect();
-    if token_data.len() == 3 {
-        let claims = base64::decode_config(token_data[1], base64::URL_SAFE)?;
-        let claims: serde_json::Value = serde_json::from_slice(&claims)?;
-        if let Some(exp) = claims.get("exp").and_then(|v| v.as_u64()) {
-            let current_time = current_timestamp();
-            if exp < current_time {
-                log!(LogLevel::Info, "Token expired, refreshing...");
-                let refresh_token = env::var("REFRESH_TOKEN").unwrap_or_default();
-                let request_body = json!({
-                    "expired_token": token,
-                    "refresh_token": refresh_token
-                });
-
-                let response = Client::new()
-                    .post(&format!("{}auth/refresh", get_base_url()))
-                    .json(&request_body)
-                    .send()
-                    .await?;
-
-                if response.status().is_success() {
-                    let json: serde_json::Value = response.json().await?;
-                    if let Some(new_token) = json.get("auth").and_then(|t| t.as_str()) {
-                        update_env_file("API_TOKEN", new_token)?;
-                        env::set_var("API_TOKEN", new_token);
-                        return env::var("API_TOKEN")
-                            .map_err(|_| "Failed to refresh token.".into());
-                    }
-                } else {
-                    log!(LogLevel::Warn, "Failed to refresh session, logging back in");
-                    let (email, password) = load_credentials()?;
-                    login(&email, &password).await?;
-                    return env::var("API_TOKEN").map_err(|_| "Failed to refresh token.".into());
-                }
-            }
-        }
+    if !token_expired(&token) {
+        return Ok(token);
     }
 
-    Ok(token)
+    log!(LogLevel::Info, "Token expired, refreshing...");
+    let session = Session {
+        token,
+        refresh_token: env::var("REFRESH_TOKEN").unwrap_or_default(),
+    };
+    match request_refresh(&api_client()?, get_base_url(), &session).await {
+        Ok(new_token) => {
+            update_env_file("API_TOKEN", &new_token)?;
+            env::set_var("API_TOKEN", &new_token);
+            Ok(new_token)
+        }
+        Err(_) => {
+            log!(LogLevel::Warn, "Failed to refresh session, logging back in");
+            let (email, password) = load_credentials()?;
+            login(&email, &password).await?;
+            env::var("API_TOKEN").map_err(|_| "Failed to refresh token.".into())
+        }
+    }
 }
 
 pub fn update_env_file(key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
@@ -129,6 +124,37 @@ pub fn update_env_file(key: &str, value: &str) -> Result<(), Box<dyn std::error:
     }
 
     fs::write(env_path, content)?;
-    log!(LogLevel::Info, "{} updated in .env file.", key);
+    log!(LogLevel::Debug, "{} updated in .env file.", key);
+    Ok(())
+}
+
+/// `KEY=VALUE` pairs in `~/.artisan_cli/.env`, in file order.
+pub fn read_env_file() -> Vec<(String, String)> {
+    let Ok(content) = app_dir().and_then(|dir| Ok(fs::read_to_string(dir.join(".env"))?)) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+pub fn remove_env_keys(keys: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let env_path = app_dir()?.join(".env");
+    let content = fs::read_to_string(&env_path)?;
+
+    let kept: String = content
+        .lines()
+        .filter(|line| {
+            !keys
+                .iter()
+                .any(|key| line.starts_with(&format!("{}=", key)))
+        })
+        .map(|line| line.to_string() + "\n")
+        .collect();
+
+    fs::write(env_path, kept)?;
     Ok(())
 }
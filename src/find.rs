@@ -0,0 +1,122 @@
+use artisan_middleware::{
+    dusa_collection_utils::{core::logger::LogLevel, log},
+    portal::{NodeDetails, NodeInfo, RunnerDetails, RunnerSummary},
+};
+use tabled::Table;
+
+use crate::{
+    defs::FindRow,
+    fetch_data,
+    file::get_token,
+    formatting::{display_table, strip_ansi_codes, style_table},
+    http::api_client,
+};
+
+fn matches(value: &str, term: &str) -> bool {
+    value.to_lowercase().contains(term)
+}
+
+/// Searches node IPs and hostnames, runner names and instance IDs for `term`
+/// and prints where each hit lives and how to inspect it.
+pub async fn find(term: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+    let term = term.trim().to_lowercase();
+    let mut rows = Vec::new();
+
+    let nodes = fetch_data::<Vec<NodeInfo>>(&client, &token, "nodes")
+        .await?
+        .unwrap_or_default();
+    for node in nodes {
+        let id = node.identity.id.to_string();
+        let status = strip_ansi_codes(&node.status.to_string());
+        let mut hit = |field: &str, value: String| {
+            rows.push(FindRow {
+                kind: "Node".to_string(),
+                id: id.clone(),
+                status: status.clone(),
+                field: field.to_string(),
+                value,
+                location: "-".to_string(),
+                command: format!("artisan_cli node get {}", id),
+            })
+        };
+
+        if matches(&id, &term) {
+            hit("Node ID", id.clone());
+        }
+        let ip = node.ip_address.to_string();
+        if matches(&ip, &term) {
+            hit("IP Address", ip);
+        }
+
+        // Hostnames only come with the node details
+        match fetch_data::<NodeDetails>(&client, &token, &format!("node/{}", id)).await {
+            Ok(Some(details)) => {
+                let hostname = details.manager_data.hostname.to_string();
+                if matches(&hostname, &term) {
+                    hit("Hostname", hostname);
+                }
+            }
+            Ok(None) => {}
+            Err(err) => log!(LogLevel::Warn, "Skipping hostname of {}: {}", id, err),
+        }
+    }
+
+    let runners = fetch_data::<Vec<RunnerSummary>>(&client, &token, "runners")
+        .await?
+        .unwrap_or_default();
+    for runner in runners {
+        let name = runner.name.to_string();
+        let short = name.replace("ais_", "");
+
+        if matches(&short, &term) || matches(&name, &term) {
+            rows.push(FindRow {
+                kind: "Runner".to_string(),
+                id: short.clone(),
+                status: strip_ansi_codes(&runner.status.to_string()),
+                field: "Name".to_string(),
+                value: name.clone(),
+                location: format!("{} node(s)", runner.nodes.len()),
+                command: format!("artisan_cli runner details {}", name),
+            });
+        }
+
+        let instances =
+            match fetch_data::<Vec<RunnerDetails>>(&client, &token, &format!("runner/{}", name))
+                .await
+            {
+                Ok(instances) => instances.unwrap_or_default(),
+                Err(err) => {
+                    log!(LogLevel::Warn, "Skipping instances of {}: {}", short, err);
+                    continue;
+                }
+            };
+
+        for instance in instances {
+            let id = instance.id.to_string();
+            if matches(&id, &term) {
+                rows.push(FindRow {
+                    kind: "Instance".to_string(),
+                    id: id.clone(),
+                    status: strip_ansi_codes(&instance.status.to_string()),
+                    field: "Instance ID".to_string(),
+                    value: id.clone(),
+                    location: format!("runner {}", short),
+                    command: format!("artisan_cli logs {}", id),
+                });
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        log!(LogLevel::Warn, "Nothing matched '{}'", term);
+        return Ok(());
+    }
+
+    let mut table = Table::new(rows);
+    table = style_table(&mut table, Some(2), false);
+    display_table(&table);
+
+    Ok(())
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use artisan_middleware::{
+    dusa_collection_utils::{
+        core::{functions::current_timestamp, logger::LogLevel},
+        log,
+    },
+    portal::{RunnerDetails, RunnerSummary},
+    timestamp::format_unix_timestamp,
+};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tabled::Table;
+
+use crate::{
+    cache::{bypass_cache, offline},
+    defs::PlanRow,
+    fetch_data,
+    file::{app_dir, get_token},
+    formatting::{display_table, strip_ansi_codes, style_table},
+    http::api_client,
+    runners::queue_control,
+    tree::same_runner,
+};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum DesiredState {
+    Running,
+    Stopped,
+}
+
+/// `fleet.yaml` / `fleet.toml`, runner name to the state it should be in:
+///
+/// ```yaml
+/// runners:
+///   ais_web: running
+///   ais_worker: stopped
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    runners: BTreeMap<String, DesiredState>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Action {
+    None,
+    Start,
+    Stop,
+    // not in `runners`, apply refuses to run
+    Unknown,
+}
+
+impl Action {
+    fn label(self) -> &'static str {
+        match self {
+            Action::None => "-",
+            Action::Start => "start",
+            Action::Stop => "stop",
+            Action::Unknown => "unknown runner",
+        }
+    }
+}
+
+struct Change {
+    runner: String,
+    status: String,
+    running: usize,
+    instances: usize,
+    desired: DesiredState,
+    action: Action,
+}
+
+#[derive(Serialize)]
+struct AppliedChange {
+    runner: String,
+    action: Action,
+    status_before: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queued_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ApplySummary {
+    applied_at: u64,
+    manifest: String,
+    changes: Vec<AppliedChange>,
+}
+
+fn load_manifest(path: &Path) -> Result<Manifest, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+
+    let manifest = match extension {
+        "yaml" | "yml" => serde_yaml::from_str(&contents).map_err(|err| err.to_string()),
+        "toml" => toml::from_str(&contents).map_err(|err| err.to_string()),
+        _ => Err("use a .yaml, .yml or .toml file".to_string()),
+    };
+    manifest.map_err(|err| format!("{}: {}", path.display(), err).into())
+}
+
+fn is_running<T: ToString>(status: T) -> bool {
+    strip_ansi_codes(&status.to_string())
+        .trim()
+        .eq_ignore_ascii_case("running")
+}
+
+/// Compares the manifest with `runners` and every listed `runner/{id}`.
+async fn plan_changes(
+    client: &Client,
+    token: &str,
+    manifest: &Manifest,
+) -> Result<Vec<Change>, Box<dyn std::error::Error>> {
+    let runners = fetch_data::<Vec<RunnerSummary>>(client, token, "runners")
+        .await?
+        .unwrap_or_default();
+
+    let mut changes = Vec::new();
+    for (name, desired) in &manifest.runners {
+        let Some(summary) = runners
+            .iter()
+            .find(|runner| same_runner(&runner.name, name))
+        else {
+            changes.push(Change {
+                runner: name.clone(),
+                status: "missing".to_string(),
+                running: 0,
+                instances: 0,
+                desired: *desired,
+                action: Action::Unknown,
+            });
+            continue;
+        };
+
+        let instances =
+            fetch_data::<Vec<RunnerDetails>>(client, token, &format!("runner/{}", summary.name))
+                .await?
+                .unwrap_or_default();
+        let running = instances
+            .iter()
+            .filter(|instance| is_running(instance.status))
+            .count();
+        let group_running = is_running(summary.status);
+
+        let action = match desired {
+            DesiredState::Running if group_running && running == instances.len() => Action::None,
+            DesiredState::Running => Action::Start,
+            DesiredState::Stopped if !group_running && running == 0 => Action::None,
+            DesiredState::Stopped => Action::Stop,
+        };
+
+        changes.push(Change {
+            runner: summary.name.to_string(),
+            status: strip_ansi_codes(&summary.status.to_string()),
+            running,
+            instances: instances.len(),
+            desired: *desired,
+            action,
+        });
+    }
+
+    Ok(changes)
+}
+
+fn print_plan(changes: &[Change]) {
+    let rows = changes
+        .iter()
+        .map(|change| PlanRow {
+            runner: change.runner.clone(),
+            status: change.status.clone(),
+            instances: format!("{}/{} running", change.running, change.instances),
+            desired: match change.desired {
+                DesiredState::Running => "running".to_string(),
+                DesiredState::Stopped => "stopped".to_string(),
+            },
+            action: change.action.label().to_string(),
+        })
+        .collect::<Vec<_>>();
+    display_table(&style_table(&mut Table::new(rows), Some(1), false));
+
+    let count = |action: Action| changes.iter().filter(|c| c.action == action).count();
+    println!(
+        "Plan: {} to start, {} to stop, {} unchanged",
+        count(Action::Start),
+        count(Action::Stop),
+        count(Action::None)
+    );
+    if count(Action::Unknown) > 0 {
+        log!(
+            LogLevel::Warn,
+            "{} runner(s) in the manifest don't exist",
+            count(Action::Unknown)
+        );
+    }
+}
+
+pub async fn plan(file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = load_manifest(file)?;
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    print_plan(&plan_changes(&client, &token, &manifest).await?);
+    Ok(())
+}
+
+fn confirm(prompt: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Plans, asks, then queues only the needed `control/*` commands and writes a
+/// summary to `~/.artisan_cli/fleet/`.
+pub async fn apply(file: &Path, yes: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if offline() {
+        return Err("apply needs the API, drop --offline".into());
+    }
+    // Plan against the live state, not whatever was cached a few seconds ago
+    let _live = bypass_cache();
+
+    let manifest = load_manifest(file)?;
+    let client = api_client()?;
+    let token = get_token().await?;
+    let changes = plan_changes(&client, &token, &manifest).await?;
+    print_plan(&changes);
+
+    let unknown = changes
+        .iter()
+        .filter(|change| change.action == Action::Unknown)
+        .map(|change| change.runner.as_str())
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        return Err(format!("Unknown runners in the manifest: {}", unknown.join(", ")).into());
+    }
+
+    let pending = changes
+        .iter()
+        .filter(|change| change.action != Action::None)
+        .collect::<Vec<_>>();
+    if pending.is_empty() {
+        log!(LogLevel::Info, "Fleet already matches {}", file.display());
+        return Ok(());
+    }
+    if !yes && !confirm(&format!("Apply {} change(s)?", pending.len()))? {
+        log!(LogLevel::Warn, "Apply cancelled, nothing was changed");
+        return Ok(());
+    }
+
+    let mut applied = Vec::new();
+    for (index, change) in pending.iter().enumerate() {
+        let command = change.action.label();
+        print!(
+            "[{}/{}] {} {} ... ",
+            index + 1,
+            pending.len(),
+            command,
+            change.runner
+        );
+        io::stdout().flush()?;
+
+        let result = queue_control(&client, &token, &change.runner, command).await;
+        match &result {
+            Ok(response) => println!("queued @ {}", format_unix_timestamp(response.queued_at)),
+            Err(err) => println!("failed: {}", err),
+        }
+        applied.push(AppliedChange {
+            runner: change.runner.clone(),
+            action: change.action,
+            status_before: change.status.clone(),
+            ok: result.is_ok(),
+            queued_at: result.as_ref().ok().map(|response| response.queued_at),
+            error: result.err().map(|err| err.to_string()),
+        });
+    }
+
+    let summary = ApplySummary {
+        applied_at: current_timestamp(),
+        manifest: file.display().to_string(),
+        changes: applied,
+    };
+    let dir = app_dir()?.join("fleet");
+    fs::create_dir_all(&dir)?;
+    let summary_file = dir.join(format!("apply-{}.json", summary.applied_at));
+    fs::write(&summary_file, serde_json::to_string_pretty(&summary)?)?;
+
+    let failed = summary.changes.iter().filter(|change| !change.ok).count();
+    println!(
+        "{} applied, {} failed, summary in {}",
+        summary.changes.len() - failed,
+        failed,
+        summary_file.display()
+    );
+    if failed > 0 {
+        return Err(format!("{} control command(s) failed", failed).into());
+    }
+    Ok(())
+}
@@ -1,21 +1,139 @@
+use artisan_middleware::dusa_collection_utils::core::functions::current_timestamp;
 use crossterm::{
     event::{self, Event, KeyCode},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode},
 };
+use owo_colors::{AnsiColors, OwoColorize, Style as OwoStyle};
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, Paragraph},
+};
+use std::{
+    cmp::Ordering,
+    env,
+    io::{self, IsTerminal},
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        OnceLock,
+    },
 };
-use std::io;
 use tabled::{
+    builder::Builder,
     settings::{
         object::{Columns, Segment},
-        Format, Modify,
+        Alignment, Format, Modify, Padding,
     },
     Table,
 };
-use unicode_width::UnicodeWidthStr; // helps account for actual display width
+
+use crate::{
+    cli::{ColorChoice, ListArgs, Palette, TableStyle},
+    defs::ListRow,
+};
+
+/// Anything last heard from longer ago than this gets the stale marker.
+pub const STALE_AFTER_SECS: u64 = 5 * 60;
+const STALE_MARKER: &str = " (stale)";
+
+static RAW_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// With raw output on durations, timestamps and byte counts are printed as
+/// exact numbers for scripts.
+pub fn set_raw_output(raw: bool) {
+    RAW_OUTPUT.store(raw, AtomicOrdering::Relaxed);
+}
+
+pub fn raw_output() -> bool {
+    RAW_OUTPUT.load(AtomicOrdering::Relaxed)
+}
+
+/// How tables and statuses are drawn, set once from the command line.
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub color: bool,
+    pub style: TableStyle,
+    pub palette: Palette,
+    pub symbols: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            color: resolve_color(ColorChoice::Auto),
+            style: TableStyle::Plain,
+            palette: Palette::Default,
+            symbols: true,
+        }
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+pub fn set_theme(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+fn theme() -> Theme {
+    *THEME.get_or_init(Theme::default)
+}
+
+/// `auto` colors only when stdout is a terminal and `NO_COLOR` isn't set,
+/// an explicit `--color always` wins over `NO_COLOR`.
+pub fn resolve_color(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                && io::stdout().is_terminal()
+        }
+    }
+}
+
+/// Renders seconds as the two most significant units, e.g. `3d 4h` or `12m 5s`.
+pub fn format_duration(secs: u64) -> String {
+    if raw_output() {
+        return secs.to_string();
+    }
+
+    let units = [(86_400, "d"), (3_600, "h"), (60, "m"), (1, "s")];
+    let parts = units
+        .iter()
+        .scan(secs, |left, (size, unit)| {
+            let amount = *left / size;
+            *left %= size;
+            Some((amount, unit))
+        })
+        .skip_while(|(amount, _)| *amount == 0)
+        .take(2)
+        .filter(|(amount, _)| *amount > 0)
+        .map(|(amount, unit)| format!("{}{}", amount, unit))
+        .collect::<Vec<_>>();
+
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// Renders a unix timestamp as `2m ago`, marked stale past `STALE_AFTER_SECS`.
+/// Values that aren't a timestamp are passed through untouched.
+pub fn format_relative<T: ToString>(timestamp: T) -> String {
+    let text = timestamp.to_string();
+    let timestamp = match text.trim().parse::<u64>() {
+        Ok(timestamp) if !raw_output() => timestamp,
+        _ => return text,
+    };
+
+    let age = current_timestamp().saturating_sub(timestamp);
+    if age > STALE_AFTER_SECS {
+        format!("{} ago{}", format_duration(age), STALE_MARKER)
+    } else {
+        format!("{} ago", format_duration(age))
+    }
+}
 
 pub fn print_logs(lines: Vec<String>, title: String) -> io::Result<()> {
     enable_raw_mode()?;
@@ -43,16 +161,105 @@ pub fn print_logs(lines: Vec<String>, title: String) -> io::Result<()> {
         })?;
 
         if event::poll(std::time::Duration::from_millis(200))? {
-            match event::read()? {
-                Event::Key(key) => match key.code {
+            if let Event::Key(key) = event::read()? {
+                match key.code {
                     KeyCode::Char('q') => break,
                     KeyCode::Down => scroll += 1,
                     KeyCode::Up => scroll = scroll.saturating_sub(1),
                     KeyCode::PageDown => scroll += 10,
                     KeyCode::PageUp => scroll = scroll.saturating_sub(10),
                     _ => {}
-                },
-                _ => {}
+                }
+            }
+        }
+    }
+
+    disable_raw_mode()?;
+    execute!(io::stdout(), crossterm::terminal::LeaveAlternateScreen)?;
+    Ok(())
+}
+
+/// A named series of `(seconds, value)` points for `show_charts`.
+pub struct ChartSeries {
+    pub name: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+/// Renders one line chart per `(title, series)` pair, stacked vertically,
+/// until 'q' is pressed.
+pub fn show_charts(charts: Vec<(String, Vec<ChartSeries>)>) -> io::Result<()> {
+    const COLORS: [Color; 6] = [
+        Color::Cyan,
+        Color::Yellow,
+        Color::Magenta,
+        Color::Green,
+        Color::Blue,
+        Color::Red,
+    ];
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    loop {
+        terminal.draw(|f| {
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(vec![
+                    Constraint::Ratio(1, charts.len().max(1) as u32);
+                    charts.len()
+                ])
+                .split(f.size());
+
+            for ((title, series), area) in charts.iter().zip(areas.iter()) {
+                let points = series.iter().flat_map(|s| s.points.iter());
+                let (mut x_max, mut y_max) = (1.0_f64, 1.0_f64);
+                for (x, y) in points {
+                    x_max = x_max.max(*x);
+                    y_max = y_max.max(*y);
+                }
+
+                let datasets = series
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| {
+                        Dataset::default()
+                            .name(s.name.clone())
+                            .marker(symbols::Marker::Braille)
+                            .graph_type(GraphType::Line)
+                            .style(Style::default().fg(COLORS[i % COLORS.len()]))
+                            .data(&s.points)
+                    })
+                    .collect::<Vec<_>>();
+
+                let chart = Chart::new(datasets)
+                    .block(
+                        Block::default()
+                            .title(format!("{} ('q' to quit)", title))
+                            .borders(Borders::ALL),
+                    )
+                    .x_axis(
+                        Axis::default()
+                            .bounds([0.0, x_max])
+                            .labels(vec![Span::raw("oldest"), Span::raw("now")]),
+                    )
+                    .y_axis(
+                        Axis::default()
+                            .bounds([0.0, y_max])
+                            .labels(vec![Span::raw("0"), Span::raw(format!("{:.1}", y_max))]),
+                    );
+
+                f.render_widget(chart, *area);
+            }
+        })?;
+
+        if event::poll(std::time::Duration::from_millis(200))? {
+            if let Event::Key(key) = event::read()? {
+                if key.code == KeyCode::Char('q') {
+                    break;
+                }
             }
         }
     }
@@ -62,51 +269,278 @@ pub fn print_logs(lines: Vec<String>, title: String) -> io::Result<()> {
     Ok(())
 }
 
+/// Squeezes `values` into a `width` character block sparkline.
+pub fn sparkline(values: &[f64], width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    if values.is_empty() || width == 0 {
+        return String::new();
+    }
+
+    let chunk = values.len().div_ceil(width);
+    let buckets = values
+        .chunks(chunk)
+        .map(|c| c.iter().sum::<f64>() / c.len() as f64)
+        .collect::<Vec<_>>();
+
+    let min = buckets.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = buckets.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    buckets
+        .iter()
+        .map(|v| {
+            if range <= f64::EPSILON {
+                BARS[0]
+            } else {
+                BARS[(((v - min) / range) * (BARS.len() - 1) as f64).round() as usize]
+            }
+        })
+        .collect()
+}
+
 pub fn style_table(mut table: &mut Table, status_col: Option<usize>, center: bool) -> Table {
     if center {
-        table = table.with(Modify::new(Segment::all()).with(tabled::settings::Alignment::center()));
+        table = table.with(Modify::new(Segment::all()).with(Alignment::center()));
     } else {
-        table = table.with(Modify::new(Segment::all()).with(tabled::settings::Alignment::left()));
+        table = table.with(Modify::new(Segment::all()).with(Alignment::left()));
     }
 
     if let Some(col) = status_col {
-        table = table.with(
-            Modify::new(Columns::single(col)).with(Format::content(|text| {
-                pad_and_color(text, std::cmp::max(4, text.len() / 2))
-            })),
-        );
+        table = table.with(Modify::new(Columns::single(col)).with(Format::content(color_status)));
     }
 
-    table
-        .with(tabled::settings::Style::empty())
-        .with(Modify::new(Segment::all()).with(tabled::settings::Alignment::center()));
+    match theme().style {
+        TableStyle::Plain => table.with(tabled::settings::Style::empty()),
+        TableStyle::Rounded => table.with(tabled::settings::Style::rounded()),
+        TableStyle::Markdown => table.with(tabled::settings::Style::markdown()),
+        TableStyle::Ascii => table.with(tabled::settings::Style::ascii()),
+        TableStyle::Compact => table
+            .with(tabled::settings::Style::empty())
+            .with(Padding::new(0, 2, 0, 0)),
+    };
     table.to_owned()
 }
 
+/// Turns a header like "Uptime (s)" or "Node ID" into the `uptime` / `node_id`
+/// key used by `--filter`, `--sort` and `--columns`.
+pub fn column_key(header: &str) -> String {
+    let base = header.split('(').next().unwrap_or(header);
+    base.trim()
+        .to_lowercase()
+        .replace('-', "_")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+// Reads `format_duration` / `format_relative` output back into seconds,
+// relative times are negative so older entries sort first
+fn duration_value(cell: &str) -> Option<f64> {
+    let cell = cell.trim().trim_end_matches(STALE_MARKER);
+    let (cell, sign) = match cell.strip_suffix(" ago") {
+        Some(cell) => (cell, -1.0),
+        None => (cell, 1.0),
+    };
+
+    let mut total = 0.0;
+    for part in cell.split_whitespace() {
+        let (amount, unit) = part.split_at(part.find(|c: char| !c.is_ascii_digit())?);
+        let amount: f64 = amount.parse().ok()?;
+        total += amount
+            * match unit {
+                "s" => 1.0,
+                "m" => 60.0,
+                "h" => 3_600.0,
+                "d" => 86_400.0,
+                _ => return None,
+            };
+    }
+    Some(total * sign)
+}
+
+// Understands the units `format_bytes` and the usage tables produce
+fn sort_value(cell: &str) -> Option<f64> {
+    if let Some(value) = duration_value(cell) {
+        return Some(value);
+    }
+
+    let cell = cell.trim();
+    let number = cell
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+        .collect::<String>();
+    let value: f64 = number.parse().ok()?;
+
+    let multiplier = match cell[number.len()..].trim() {
+        "KB" => 1024.0,
+        "MB" => 1024.0 * 1024.0,
+        "GB" => 1024.0 * 1024.0 * 1024.0,
+        "TB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => 1.0,
+    };
+    Some(value * multiplier)
+}
+
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (sort_value(a), sort_value(b)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+fn find_column(keys: &[String], wanted: &str) -> Result<usize, String> {
+    let wanted = column_key(wanted);
+    keys.iter().position(|key| *key == wanted).ok_or_else(|| {
+        format!(
+            "Unknown column '{}', expected one of: {}",
+            wanted,
+            keys.join(", ")
+        )
+    })
+}
+
+/// Applies the shared list flags (filter, sort, limit, column selection and
+/// wide columns) to `rows` and prints the result.
+pub fn display_rows<T: ListRow>(
+    rows: Vec<T>,
+    args: &ListArgs,
+    center: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let headers = T::headers()
+        .iter()
+        .map(|header| header.to_string())
+        .collect::<Vec<_>>();
+    let keys = headers.iter().map(|h| column_key(h)).collect::<Vec<_>>();
+
+    let mut records = rows
+        .iter()
+        .map(|row| {
+            row.fields()
+                .iter()
+                .map(|field| field.to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for filter in &args.filter {
+        let (column, expected, negate) = match filter.split_once("!=") {
+            Some((column, expected)) => (column, expected, true),
+            None => match filter.split_once('=') {
+                Some((column, expected)) => (column, expected, false),
+                None => return Err(format!("Filter '{}' isn't COLUMN=VALUE", filter).into()),
+            },
+        };
+        let index = find_column(&keys, column)?;
+        records.retain(|record| record[index].eq_ignore_ascii_case(expected.trim()) != negate);
+    }
+
+    if let Some(sort) = &args.sort {
+        let index = find_column(&keys, sort)?;
+        records.sort_by(|a, b| compare_cells(&a[index], &b[index]));
+        if args.desc {
+            records.reverse();
+        }
+    }
+
+    if let Some(limit) = args.limit {
+        records.truncate(limit);
+    }
+
+    let selected = if args.columns.is_empty() {
+        (0..keys.len())
+            .filter(|index| args.wide || !T::WIDE_COLUMNS.contains(&keys[*index].as_str()))
+            .collect::<Vec<_>>()
+    } else {
+        args.columns
+            .iter()
+            .map(|column| find_column(&keys, column))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut builder = Builder::new();
+    builder.push_record(selected.iter().map(|index| headers[*index].clone()));
+    for record in &records {
+        builder.push_record(selected.iter().map(|index| record[*index].clone()));
+    }
+
+    let status_col = selected.iter().position(|index| keys[*index] == "status");
+    let mut table = builder.build();
+    table = style_table(&mut table, status_col, center);
+    display_table(&table);
+
+    Ok(())
+}
+
 pub fn display_table(table: &Table) {
     println!();
     println!("{}", table);
     println!();
 }
 
-pub fn pad_and_color(text: &str, width: usize) -> String {
-    let display_width = UnicodeWidthStr::width(text);
-    let padding = width.saturating_sub(display_width) * 4;
-    let left = padding / 2;
-    let right = padding - left;
+enum StatusClass {
+    Healthy,
+    Degraded,
+    Down,
+    Other,
+}
+
+fn classify_status(status: &str) -> StatusClass {
+    match status.trim().to_lowercase().as_str() {
+        "running" | "online" | "active" | "healthy" | "ok" => StatusClass::Healthy,
+        "warning" | "degraded" | "starting" | "restarting" | "stopping" | "pending" => {
+            StatusClass::Degraded
+        }
+        "stopped" | "offline" | "failed" | "error" | "crashed" | "missing" | "unknown" => {
+            StatusClass::Down
+        }
+        _ => StatusClass::Other,
+    }
+}
+
+/// Colors a status cell for the active palette and prefixes a symbol so the
+/// state still reads without color. The "Status" header is only bolded.
+pub fn color_status(text: &str) -> String {
+    let theme = theme();
+    if text.trim().is_empty() {
+        return text.to_string();
+    }
+    if text == "Status" {
+        return heading(text);
+    }
+
+    let class = classify_status(text);
+    let (symbol, color) = match (&class, theme.palette) {
+        (StatusClass::Healthy, Palette::Default) => ("✔", AnsiColors::Green),
+        (StatusClass::Degraded, Palette::Default) => ("▲", AnsiColors::Yellow),
+        (StatusClass::Down, Palette::Default) => ("✖", AnsiColors::Red),
+        // Blue / yellow / magenta stay distinct for the common color vision deficiencies
+        (StatusClass::Healthy, Palette::Colorblind) => ("✔", AnsiColors::BrightBlue),
+        (StatusClass::Degraded, Palette::Colorblind) => ("▲", AnsiColors::BrightYellow),
+        (StatusClass::Down, Palette::Colorblind) => ("✖", AnsiColors::BrightMagenta),
+        (StatusClass::Other, _) => ("•", AnsiColors::Cyan),
+    };
+
+    let text = if theme.symbols {
+        format!("{} {}", symbol, text)
+    } else {
+        text.to_string()
+    };
+
+    if theme.color {
+        OwoColorize::style(&text, OwoStyle::new().color(color).bold()).to_string()
+    } else {
+        text
+    }
+}
 
-    format!(
-        "{}{}{}",
-        "".repeat(left),
-        match text.to_string().as_str() {
-            "Running" => text.green().bold().to_string(),
-            "Warning" => text.yellow().bold().to_string(),
-            "Stopped" => text.red().bold().to_string(),
-            "Status" => text.white().bold().to_string(),
-            _ => text.to_string(),
-        },
-        "".repeat(right)
-    )
+/// Bold section title, plain when color is off.
+pub fn heading(text: &str) -> String {
+    if theme().color {
+        OwoColorize::bold(&text).to_string()
+    } else {
+        text.to_string()
+    }
 }
 
 pub fn strip_ansi_codes(s: &str) -> String {
@@ -114,7 +548,18 @@ pub fn strip_ansi_codes(s: &str) -> String {
     re.replace_all(s, "").to_string()
 }
 
+pub fn join_ids<T: ToString>(ids: &[T]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 pub fn format_bytes(bytes: u64) -> String {
+    if raw_output() {
+        return bytes.to_string();
+    }
+
     const KB: f64 = 1024.0;
     const MB: f64 = KB * 1024.0;
     const GB: f64 = MB * 1024.0;
@@ -0,0 +1,350 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use artisan_middleware::dusa_collection_utils::{core::logger::LogLevel, log};
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Certificate, Client, Identity, Proxy, RequestBuilder, Response, StatusCode,
+};
+use serde_json::Value;
+use tokio::time::sleep;
+
+const BACKOFF_BASE_MS: u64 = 500;
+const BACKOFF_CAP_MS: u64 = 10_000;
+// A server asking us to wait longer than this is treated as down
+const MAX_RETRY_AFTER_SECS: u64 = 60;
+const MAX_TRACE_BODY: usize = 2000;
+
+// Never printed by --trace-http, matched case insensitively
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+const SECRET_FIELDS: &[&str] = &[
+    "password",
+    "auth",
+    "refresh",
+    "token",
+    "refresh_token",
+    "expired_token",
+    "api_token",
+    "secret",
+];
+
+/// Timeouts, retry behaviour and TLS / proxy settings shared by every API
+/// call, set once from the command line.
+pub struct HttpPolicy {
+    pub timeout: u64,
+    pub connect_timeout: u64,
+    pub retries: u32,
+    pub retry_unsafe: bool,
+    pub proxy: Option<String>,
+    pub ca_bundles: Vec<PathBuf>,
+    // (certificate, PKCS#8 key), both PEM
+    pub client_identity: Option<(PathBuf, PathBuf)>,
+    pub insecure: bool,
+    // print every request and response to stderr, secrets redacted
+    pub trace: bool,
+}
+
+impl Default for HttpPolicy {
+    fn default() -> Self {
+        HttpPolicy {
+            timeout: 30,
+            connect_timeout: 10,
+            retries: 3,
+            retry_unsafe: false,
+            proxy: None,
+            ca_bundles: Vec::new(),
+            client_identity: None,
+            insecure: false,
+            trace: false,
+        }
+    }
+}
+
+static POLICY: OnceLock<HttpPolicy> = OnceLock::new();
+
+pub fn set_http_policy(policy: HttpPolicy) {
+    if policy.insecure {
+        log!(
+            LogLevel::Warn,
+            "--insecure: TLS certificates are NOT being verified, don't use this outside local testing"
+        );
+    }
+    let _ = POLICY.set(policy);
+}
+
+fn policy() -> &'static HttpPolicy {
+    POLICY.get_or_init(HttpPolicy::default)
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    fs::read(path).map_err(|err| format!("Failed to read {}: {}", path.display(), err).into())
+}
+
+/// Builds a client with the configured timeouts, proxy, extra CAs and client
+/// certificate, use it instead of `Client::new()` for anything that talks to
+/// the API. It's built once per process, so `run` scripts and `shell` share
+/// one connection pool.
+pub fn api_client() -> Result<Client, Box<dyn std::error::Error>> {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    if let Some(client) = CLIENT.get() {
+        return Ok(client.clone());
+    }
+    let policy = policy();
+    let client = build_client(
+        Duration::from_secs(policy.timeout),
+        Duration::from_secs(policy.connect_timeout),
+    )?;
+    Ok(CLIENT.get_or_init(|| client).clone())
+}
+
+/// Same proxy and TLS settings as `api_client` but with a tight `timeout`,
+/// for callers that can't afford to wait, like tab completion. Send with
+/// `send`, not `send_get`, so nothing is retried either.
+pub fn quick_client(timeout: Duration) -> Result<Client, Box<dyn std::error::Error>> {
+    build_client(timeout, timeout)
+}
+
+fn build_client(
+    timeout: Duration,
+    connect_timeout: Duration,
+) -> Result<Client, Box<dyn std::error::Error>> {
+    let policy = policy();
+    let mut builder = Client::builder()
+        .timeout(timeout)
+        .connect_timeout(connect_timeout)
+        .danger_accept_invalid_certs(policy.insecure);
+
+    if let Some(proxy) = &policy.proxy {
+        builder = builder.proxy(Proxy::https(proxy)?);
+    }
+
+    for bundle in &policy.ca_bundles {
+        for certificate in Certificate::from_pem_bundle(&read_pem(bundle)?)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let Some((cert, key)) = &policy.client_identity {
+        builder = builder.identity(Identity::from_pkcs8_pem(&read_pem(cert)?, &read_pem(key)?)?);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Exponential backoff with jitter, somewhere between half and all of
+/// `BACKOFF_BASE_MS * 2^(attempt - 1)`.
+fn backoff(attempt: u32) -> Duration {
+    let ceiling = BACKOFF_BASE_MS
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(BACKOFF_CAP_MS);
+    // Good enough randomness to keep a fleet of scripts from retrying in lockstep
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.subsec_nanos() as u64);
+    Duration::from_millis(ceiling / 2 + nanos % (ceiling / 2 + 1))
+}
+
+fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                if SECRET_FIELDS.contains(&key.to_lowercase().as_str()) {
+                    *field = Value::String("<redacted>".to_string());
+                } else {
+                    redact_json(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+fn trace_headers(headers: &HeaderMap) {
+    for (name, value) in headers {
+        let value = if SECRET_HEADERS.contains(&name.as_str()) {
+            "<redacted>"
+        } else {
+            value.to_str().unwrap_or("<binary>")
+        };
+        eprintln!("  {}: {}", name, value);
+    }
+}
+
+fn trace_body(body: &[u8]) {
+    if body.is_empty() {
+        return;
+    }
+    let text = match serde_json::from_slice::<Value>(body) {
+        Ok(mut json) => {
+            redact_json(&mut json);
+            json.to_string()
+        }
+        Err(_) => String::from_utf8_lossy(body).to_string(),
+    };
+    match text.char_indices().nth(MAX_TRACE_BODY) {
+        Some((end, _)) => eprintln!("  {}... ({} bytes)", &text[..end], body.len()),
+        None => eprintln!("  {}", text),
+    }
+}
+
+/// Sends `request`, printing it and its response with `--trace-http`. Every
+/// API call goes through here so the trace is complete.
+pub async fn send(request: RequestBuilder) -> Result<Response, reqwest::Error> {
+    if !policy().trace {
+        return request.send().await;
+    }
+
+    let (client, request) = request.build_split();
+    let request = request?;
+    eprintln!("> {} {}", request.method(), request.url());
+    trace_headers(request.headers());
+    if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+        trace_body(body);
+    }
+
+    let started = Instant::now();
+    let response = match client.execute(request).await {
+        Ok(response) => response,
+        Err(err) => {
+            eprintln!(
+                "< failed after {}ms: {}",
+                started.elapsed().as_millis(),
+                err
+            );
+            return Err(err);
+        }
+    };
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+
+    eprintln!("< {} in {}ms", status, started.elapsed().as_millis());
+    trace_headers(&headers);
+    trace_body(&body);
+
+    // The body was read for the trace, hand back an identical response
+    let mut rebuilt = http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    Ok(Response::from(rebuilt))
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date, a date
+/// that has already passed means retry now.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, SystemTime::now())
+}
+
+fn retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Sends a GET for `path`, retrying timeouts, connection failures, 429s and
+/// 502/503/504s with backoff. `control/*` and `discover` change server state
+/// so they're only retried with `--retry-unsafe`.
+pub async fn send_get(
+    request: RequestBuilder,
+    path: &str,
+) -> Result<Response, Box<dyn std::error::Error>> {
+    let policy = policy();
+    let changes_state = path.starts_with("control/") || path == "discover";
+    let retries = if changes_state && !policy.retry_unsafe {
+        0
+    } else {
+        policy.retries
+    };
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let result = send(request.try_clone().ok_or("Request can't be retried")?).await;
+
+        let (reason, wait) = match &result {
+            Ok(response) if retryable_status(response.status()) => {
+                let wait = match retry_after(response) {
+                    Some(wait) if wait.as_secs() > MAX_RETRY_AFTER_SECS => {
+                        log!(
+                            LogLevel::Warn,
+                            "{} asked us to wait {}s, giving up",
+                            path,
+                            wait.as_secs()
+                        );
+                        return Ok(result?);
+                    }
+                    Some(wait) => wait,
+                    None => backoff(attempt),
+                };
+                (response.status().to_string(), wait)
+            }
+            Err(err) if err.is_timeout() || err.is_connect() => (err.to_string(), backoff(attempt)),
+            _ => return Ok(result?),
+        };
+
+        if attempt > retries {
+            return Ok(result?);
+        }
+
+        log!(
+            LogLevel::Warn,
+            "{} failed ({}), retrying in {:.1}s ({}/{})",
+            path,
+            reason,
+            wait.as_secs_f32(),
+            attempt,
+            retries
+        );
+        sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_takes_seconds_or_a_date() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            parse_retry_after(" 120 ", now),
+            Some(Duration::from_secs(120))
+        );
+        // 2023-11-14T22:13:20Z is `now`
+        assert_eq!(
+            parse_retry_after("Tue, 14 Nov 2023 22:13:50 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Tue, 14 Nov 2023 22:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
@@ -0,0 +1,66 @@
+//! The Artisan Hosting portal client behind `artisan_cli`. Other tools should
+//! start from [`sdk::ArtisanClient`], the remaining modules are the CLI's
+//! commands and output helpers.
+
+use std::{env, sync::OnceLock};
+
+use artisan_middleware::portal::ApiResponse;
+use reqwest::Client;
+
+use cache::cached_get;
+use config::config;
+
+pub mod alias;
+pub mod audit;
+pub mod auth;
+pub mod cache;
+pub mod cli;
+pub mod commands;
+pub mod completions;
+pub mod config;
+pub mod defs;
+pub mod doctor;
+pub mod events;
+pub mod exporter;
+pub mod file;
+pub mod find;
+pub mod fleet;
+pub mod formatting;
+pub mod http;
+pub mod metrics;
+pub mod nodes;
+pub mod run;
+pub mod runners;
+pub mod sdk;
+pub mod shell;
+pub mod tree;
+pub mod usage;
+
+/// GETs `path` and unwraps the `ApiResponse` payload, non-success statuses are
+/// returned as errors.
+pub async fn fetch_data<T: serde::de::DeserializeOwned>(
+    client: &Client,
+    token: &str,
+    path: &str,
+) -> Result<Option<T>, Box<dyn std::error::Error>> {
+    let response = cached_get(client, token, path).await?;
+
+    if !response.status().is_success() {
+        return Err(format!("{} returned {}", path, response.status()).into());
+    }
+
+    let api_response: ApiResponse<T> = response.json()?;
+    Ok(api_response.data)
+}
+
+// ARTISAN_API_URL points the CLI at another portal, e.g. the mock one the tests run
+pub fn get_base_url() -> &'static str {
+    static BASE_URL: OnceLock<String> = OnceLock::new();
+    BASE_URL.get_or_init(|| {
+        let url = match env::var("ARTISAN_API_URL") {
+            Ok(url) if !url.is_empty() => url,
+            _ => config().api_url.clone(),
+        };
+        format!("{}/", url.trim_end_matches('/'))
+    })
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    time::Duration,
+};
+
+use artisan_middleware::{
+    dusa_collection_utils::{
+        core::{functions::current_timestamp, logger::LogLevel},
+        log,
+    },
+    portal::{ApiResponse, RunnerDetails, RunnerSummary},
+};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tabled::Table;
+use tokio::time::sleep;
+
+use crate::{
+    cache::{bypass_cache, cached_get, offline},
+    defs::MetricTrendRow,
+    file::{app_dir, get_token},
+    formatting::{
+        display_table, format_bytes, show_charts, sparkline, strip_ansi_codes, style_table,
+        ChartSeries,
+    },
+    http::api_client,
+};
+
+const SPARKLINE_WIDTH: usize = 24;
+
+/// One health sample of a single instance, stored as a line of `metrics.jsonl`.
+#[derive(Serialize, Deserialize)]
+pub struct MetricSample {
+    pub timestamp: u64,
+    pub runner_id: String,
+    pub instance_id: String,
+    pub status: String,
+    pub cpu: Option<f64>,
+    pub ram: Option<f64>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub uptime: u64,
+}
+
+fn metrics_file() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(app_dir()?.join("metrics.jsonl"))
+}
+
+// The portal reports usage as display strings like "12.5%" or "256 MB"
+pub fn parse_leading_number(value: &str) -> Option<f64> {
+    let number = value
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect::<String>();
+    number.parse().ok()
+}
+
+async fn fetch_runner_names(
+    client: &Client,
+    token: &str,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let response = cached_get(client, token, "runners").await?;
+
+    if !response.status().is_success() {
+        return Err(format!("Failed to list runners: {}", response.text()).into());
+    }
+
+    let api_response: ApiResponse<Vec<RunnerSummary>> = response.json()?;
+    Ok(api_response
+        .data
+        .unwrap_or_default()
+        .into_iter()
+        .map(|runner| runner.name.to_string())
+        .collect())
+}
+
+async fn sample_runner(
+    client: &Client,
+    token: &str,
+    runner_id: &str,
+) -> Result<Vec<MetricSample>, Box<dyn std::error::Error>> {
+    let response = cached_get(client, token, &format!("runner/{}", runner_id)).await?;
+
+    if !response.status().is_success() {
+        return Err(format!("Failed to get runner details: {}", response.text()).into());
+    }
+
+    let api_response: ApiResponse<Vec<RunnerDetails>> = response.json()?;
+    let timestamp = current_timestamp();
+
+    Ok(api_response
+        .data
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|runner| {
+            let health = runner.health?;
+            Some(MetricSample {
+                timestamp,
+                runner_id: runner_id.to_string(),
+                instance_id: runner.id.to_string(),
+                status: strip_ansi_codes(&runner.status.to_string()),
+                cpu: parse_leading_number(&health.cpu_usage.to_string()),
+                ram: parse_leading_number(&health.ram_usage.to_string()),
+                rx_bytes: health.rx_bytes,
+                tx_bytes: health.tx_bytes,
+                uptime: health.uptime,
+            })
+        })
+        .collect())
+}
+
+/// Samples `runner/{id}` every `interval` seconds and appends the results to
+/// `~/.artisan_cli/metrics.jsonl`. Records every runner when none are given.
+pub async fn record_metrics(
+    runner_ids: &[String],
+    interval: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if interval < 10 {
+        log!(
+            LogLevel::Error,
+            "Recording more often than every 10 seconds isn't supported"
+        );
+        return Ok(());
+    }
+    if offline() {
+        return Err("Recording needs the API, drop --offline".into());
+    }
+    // Every sample has to be a new reading, not the cached one again
+    let _live = bypass_cache();
+
+    let path = metrics_file()?;
+    let client = api_client()?;
+    log!(
+        LogLevel::Info,
+        "Recording metrics every {}s into {}",
+        interval,
+        path.display()
+    );
+
+    loop {
+        // Fetched every round so long running recorders survive token refreshes
+        let token = get_token().await?;
+
+        let runners = if runner_ids.is_empty() {
+            match fetch_runner_names(&client, &token).await {
+                Ok(names) => names,
+                Err(err) => {
+                    log!(LogLevel::Error, "{}", err);
+                    Vec::new()
+                }
+            }
+        } else {
+            runner_ids.to_vec()
+        };
+
+        let mut lines = String::new();
+        let mut count = 0;
+        for runner_id in &runners {
+            match sample_runner(&client, &token, runner_id).await {
+                Ok(samples) => {
+                    for sample in samples {
+                        lines.push_str(&serde_json::to_string(&sample)?);
+                        lines.push('\n');
+                        count += 1;
+                    }
+                }
+                Err(err) => log!(LogLevel::Error, "{}: {}", runner_id, err),
+            }
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.write_all(lines.as_bytes())?;
+        log!(LogLevel::Debug, "Recorded {} samples", count);
+
+        sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+fn load_samples(
+    runner_id: Option<&str>,
+    since: u64,
+) -> Result<Vec<MetricSample>, Box<dyn std::error::Error>> {
+    let path = metrics_file()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let cutoff = current_timestamp().saturating_sub(since);
+    let samples = fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str::<MetricSample>(line).ok())
+        .filter(|sample| sample.timestamp >= cutoff)
+        .filter(|sample| runner_id.is_none_or(|id| sample.runner_id == id))
+        .collect();
+
+    Ok(samples)
+}
+
+/// Shows the recorded history as sparklines, or as full screen line charts
+/// when `chart` is set.
+pub fn show_metrics(
+    runner_id: Option<&str>,
+    since: u64,
+    chart: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let samples = load_samples(runner_id, since)?;
+    if samples.is_empty() {
+        log!(
+            LogLevel::Warn,
+            "No metrics recorded for that window, start a recorder with 'metrics record'"
+        );
+        return Ok(());
+    }
+
+    let mut by_instance: BTreeMap<String, Vec<MetricSample>> = BTreeMap::new();
+    for sample in samples {
+        by_instance
+            .entry(sample.instance_id.clone())
+            .or_default()
+            .push(sample);
+    }
+
+    if chart {
+        let start = by_instance
+            .values()
+            .flatten()
+            .map(|sample| sample.timestamp)
+            .min()
+            .unwrap_or_default();
+
+        let series = |pick: fn(&MetricSample) -> Option<f64>| {
+            by_instance
+                .iter()
+                .map(|(id, samples)| ChartSeries {
+                    name: id.clone(),
+                    points: samples
+                        .iter()
+                        .filter_map(|s| Some(((s.timestamp - start) as f64, pick(s)?)))
+                        .collect(),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        show_charts(vec![
+            ("CPU Usage".to_string(), series(|s| s.cpu)),
+            ("RAM Usage".to_string(), series(|s| s.ram)),
+        ])?;
+        return Ok(());
+    }
+
+    let rows = by_instance
+        .into_iter()
+        .map(|(instance_id, samples)| {
+            let cpu = samples.iter().filter_map(|s| s.cpu).collect::<Vec<_>>();
+            let ram = samples.iter().filter_map(|s| s.ram).collect::<Vec<_>>();
+            let peak = |values: &[f64]| values.iter().cloned().fold(0.0, f64::max);
+            let (first, last) = (&samples[0], &samples[samples.len() - 1]);
+
+            MetricTrendRow {
+                instance_id,
+                runner_id: last.runner_id.clone(),
+                status: last.status.clone(),
+                samples: samples.len().to_string(),
+                cpu_trend: sparkline(&cpu, SPARKLINE_WIDTH),
+                cpu_peak: format!("{:.2}", peak(&cpu)),
+                ram_trend: sparkline(&ram, SPARKLINE_WIDTH),
+                ram_peak: format!("{:.2}", peak(&ram)),
+                rx: format_bytes(last.rx_bytes.saturating_sub(first.rx_bytes)),
+                tx: format_bytes(last.tx_bytes.saturating_sub(first.tx_bytes)),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut table = Table::new(rows);
+    table = style_table(&mut table, Some(2), false);
+    display_table(&table);
+
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use artisan_middleware::{
+    dusa_collection_utils::{core::logger::LogLevel, log},
+    portal::{ApiResponse, NodeDetails, NodeInfo, RunnerSummary},
+};
+use tabled::Table;
+
+use crate::{
+    cache::cached_get,
+    cli::{ListArgs, NodeSection},
+    defs::{GenericRow, ListRow, NodeRow, NodeSummaryRow, RunnerRow},
+    fetch_data,
+    file::get_token,
+    formatting::{
+        color_status, display_rows, display_table, format_duration, format_relative, heading,
+        join_ids, strip_ansi_codes, style_table,
+    },
+    http::api_client,
+};
+
+pub async fn list_nodes(list: &ListArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    let response = cached_get(&client, &token, "nodes").await?;
+
+    if response.status().is_success() {
+        let api_response: ApiResponse<Vec<NodeInfo>> = response.json()?;
+        if let Some(nodes) = api_response.data {
+            println!();
+
+            let rows = nodes
+                .into_iter()
+                .map(|node| NodeRow {
+                    id: node.identity.id.to_string(),
+                    status: strip_ansi_codes(&node.status.to_string()),
+                    ip: node.ip_address.to_string(),
+                    runner_count: node.runners.len().to_string(),
+                    updated: format_relative(&node.last_updated),
+                    runners: join_ids(&node.runners),
+                })
+                .collect::<Vec<_>>();
+
+            display_rows(rows, list, true)?;
+        } else {
+            log!(
+                LogLevel::Error,
+                "Get a drink. Currently there are no nodes registered"
+            );
+        }
+    } else {
+        return Err(format!("Failed to list nodes: {}", response.text()).into());
+    }
+
+    Ok(())
+}
+
+pub async fn get_node(
+    node_id: &str,
+    sections: &[NodeSection],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    let response = cached_get(&client, &token, &format!("node/{}", node_id)).await?;
+
+    if response.status().is_success() {
+        let api_response: ApiResponse<NodeDetails> = response.json()?;
+        if let Some(node) = api_response.data {
+            // Summary is the old single row view, kept for scripts that parse it
+            let show = |section: NodeSection| {
+                sections.contains(&section)
+                    || (sections.is_empty() && section != NodeSection::Summary)
+            };
+            let status = strip_ansi_codes(&node.status.to_string());
+            let generic = |rows: Vec<(&str, String)>| {
+                rows.into_iter()
+                    .map(|(key, value)| GenericRow {
+                        key: key.to_string(),
+                        value,
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            if show(NodeSection::Summary) {
+                let row = NodeSummaryRow {
+                    node_id: node.identity.id.to_string(),
+                    status: status.clone(),
+                    client_apps: node.manager_data.client_apps,
+                    system_apps: node.manager_data.system_apps,
+                    hostname: node.manager_data.hostname.to_string(),
+                    ip_address: node.manager_data.address.to_string(),
+                    warnings: node.manager_data.warning,
+                    last_updated: format_relative(&node.last_updated),
+                };
+
+                let mut table = Table::new(vec![row]);
+                table = style_table(&mut table, Some(1), true); // Color status column, center align
+                display_table(&table);
+            }
+
+            if show(NodeSection::Identity) {
+                print_section(
+                    "Identity",
+                    generic(vec![
+                        ("Node ID", node.identity.id.to_string()),
+                        ("Hostname", node.manager_data.hostname.to_string()),
+                        ("IP Address", node.manager_data.address.to_string()),
+                    ]),
+                )?;
+            }
+
+            if show(NodeSection::Status) {
+                print_section(
+                    "Status",
+                    generic(vec![
+                        ("Status", color_status(&status)),
+                        ("Registered", format_relative(&node.created_at)),
+                        ("Last Updated", format_relative(&node.last_updated)),
+                    ]),
+                )?;
+            }
+
+            if show(NodeSection::Manager) {
+                print_section(
+                    "Manager Data",
+                    generic(vec![
+                        ("Hostname", node.manager_data.hostname.to_string()),
+                        ("Address", node.manager_data.address.to_string()),
+                        ("Client Apps", node.manager_data.client_apps.to_string()),
+                        ("System Apps", node.manager_data.system_apps.to_string()),
+                        ("Warnings", node.manager_data.warning.to_string()),
+                    ]),
+                )?;
+            }
+
+            if show(NodeSection::Runners) || show(NodeSection::Warnings) {
+                let hosted = node
+                    .runners
+                    .iter()
+                    .map(|runner| runner.to_string())
+                    .collect::<Vec<_>>();
+                let summaries =
+                    match fetch_data::<Vec<RunnerSummary>>(&client, &token, "runners").await {
+                        Ok(runners) => runners.unwrap_or_default(),
+                        Err(err) => {
+                            log!(LogLevel::Warn, "Failed to get runner statuses: {}", err);
+                            Vec::new()
+                        }
+                    };
+
+                let rows = hosted
+                    .iter()
+                    .map(|name| {
+                        let short = name.replace("ais_", "");
+                        let summary = summaries
+                            .iter()
+                            .find(|r| r.name.replace("ais_", "") == short);
+
+                        RunnerRow {
+                            name: short,
+                            status: summary.map_or("Unknown".to_string(), |r| {
+                                strip_ansi_codes(&r.status.to_string())
+                            }),
+                            uptime: format_duration(summary.map_or(0, |r| r.uptime.unwrap_or(0))),
+                            instances: summary.map_or(0, |r| r.nodes.len()).to_string(),
+                            nodes: summary.map_or(String::new(), |r| join_ids(&r.nodes)),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                if show(NodeSection::Warnings) {
+                    // The portal only reports a count, the runners that aren't
+                    // running are what raise it
+                    let troubled = rows
+                        .iter()
+                        .filter(|row| row.status != "Running")
+                        .cloned()
+                        .collect::<Vec<_>>();
+
+                    println!("{} ({})", heading("Warnings"), node.manager_data.warning);
+                    if troubled.is_empty() {
+                        println!("\n  No runners on this node are reporting problems.\n");
+                    } else {
+                        print_section("", troubled)?;
+                    }
+                }
+
+                if show(NodeSection::Runners) {
+                    if rows.is_empty() {
+                        println!("{}", heading("Runners"));
+                        println!("\n  This node isn't hosting any runners.\n");
+                    } else {
+                        print_section("Runners", rows)?;
+                    }
+                }
+            }
+        } else {
+            return Err(format!("Node {} not found", node_id).into());
+        }
+    } else {
+        return Err(format!("Failed to get node details: {}", response.text()).into());
+    }
+
+    Ok(())
+}
+
+fn print_section<T: ListRow>(title: &str, rows: Vec<T>) -> Result<(), Box<dyn std::error::Error>> {
+    if !title.is_empty() {
+        println!("{}", heading(title));
+    }
+    display_rows(rows, &ListArgs::default(), false)
+}
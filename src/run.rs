@@ -0,0 +1,224 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+    time::Instant,
+};
+
+use artisan_middleware::dusa_collection_utils::{core::logger::LogLevel, log};
+use clap::Parser;
+use tabled::Table;
+
+use crate::{
+    alias::expand_aliases,
+    cli::{MetricsCmd, TopLevelCommand},
+    commands::execute,
+    defs::RunRow,
+    formatting::{display_table, style_table},
+};
+
+/// One line of a script, the same grammar as the binary minus its name and
+/// the global flags, which apply to the whole run.
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+pub struct ScriptLine {
+    #[command(subcommand)]
+    pub command: TopLevelCommand,
+}
+
+/// Splits a line into words the way a shell would for simple cases: single
+/// and double quotes group, a backslash escapes the next character.
+pub fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = line.chars();
+
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (Some(open), ch) if ch == open => quote = None,
+            (Some('"'), '\\') | (None, '\\') => match chars.next() {
+                Some(escaped) => {
+                    word.push(escaped);
+                    in_word = true;
+                }
+                None => return Err("trailing backslash".to_string()),
+            },
+            (Some(_), ch) => word.push(ch),
+            (None, '\'' | '"') => {
+                quote = Some(ch);
+                in_word = true;
+            }
+            (None, ch) if ch.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, ch) => {
+                word.push(ch);
+                in_word = true;
+            }
+        }
+    }
+
+    if let Some(open) = quote {
+        return Err(format!("unclosed {} quote", open));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Words of a script line with an optional leading `artisan_cli` dropped,
+/// `None` for blank lines and `#` comments.
+pub fn script_words(line: &str) -> Result<Option<Vec<String>>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut words = split_words(line)?;
+    if words.first().is_some_and(|word| word == "artisan_cli") {
+        words.remove(0);
+    }
+    Ok((!words.is_empty()).then_some(words))
+}
+
+fn parse_line(words: &[String]) -> Result<TopLevelCommand, String> {
+    let words = expand_aliases(words.to_vec(), 0)?;
+    let line = ScriptLine::try_parse_from(words).map_err(|err| {
+        // clap's rendering ends with a usage block, the first line is enough here
+        err.to_string()
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches("error: ")
+            .to_string()
+    })?;
+    // Nested runs and the commands that never return would stall the script
+    let refused = match &line.command {
+        TopLevelCommand::Run { .. } => Some("run"),
+        TopLevelCommand::Shell => Some("shell"),
+        TopLevelCommand::Exporter { .. } => Some("exporter"),
+        TopLevelCommand::WatchEvents { .. } => Some("watch-events"),
+        TopLevelCommand::Metrics(MetricsCmd::Record { .. }) => Some("metrics record"),
+        _ => None,
+    };
+    if let Some(name) = refused {
+        return Err(format!("scripts can't call `{}`", name));
+    }
+    Ok(line.command)
+}
+
+struct Outcome {
+    line: usize,
+    command: String,
+    result: &'static str,
+    millis: Option<u128>,
+    error: Option<String>,
+}
+
+/// Runs every command in `script` (stdin when it's `None` or `-`) in this
+/// process, so they share one session and HTTP client. Stops at the first
+/// failure unless `continue_on_error` is set, then prints a report.
+pub async fn run_script(
+    script: Option<&Path>,
+    continue_on_error: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = match script {
+        Some(path) if path != Path::new("-") => fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?,
+        _ => {
+            let mut contents = String::new();
+            io::stdin().read_to_string(&mut contents)?;
+            contents
+        }
+    };
+
+    // Parse everything up front so a typo on the last line doesn't leave the
+    // first half of a script applied
+    let mut lines = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let words = script_words(line).map_err(|err| format!("line {}: {}", index + 1, err))?;
+        if let Some(words) = words {
+            let command =
+                parse_line(&words).map_err(|err| format!("line {}: {}", index + 1, err))?;
+            lines.push((index + 1, words.join(" "), command));
+        }
+    }
+    if lines.is_empty() {
+        log!(LogLevel::Warn, "The script has no commands");
+        return Ok(());
+    }
+
+    let mut outcomes = Vec::new();
+    let mut stopped = false;
+    for (line, text, command) in lines {
+        if stopped {
+            outcomes.push(Outcome {
+                line,
+                command: text,
+                result: "skipped",
+                millis: None,
+                error: None,
+            });
+            continue;
+        }
+
+        log!(LogLevel::Debug, "line {}: {}", line, text);
+        let started = Instant::now();
+        // Boxed since `execute` is what called us
+        let result = Box::pin(execute(&command)).await;
+        let millis = Some(started.elapsed().as_millis());
+        match result {
+            Ok(()) => outcomes.push(Outcome {
+                line,
+                command: text,
+                result: "ok",
+                millis,
+                error: None,
+            }),
+            Err(err) => {
+                log!(LogLevel::Error, "line {}: {}", line, err);
+                stopped = !continue_on_error;
+                outcomes.push(Outcome {
+                    line,
+                    command: text,
+                    result: "failed",
+                    millis,
+                    error: Some(err.to_string()),
+                });
+            }
+        }
+    }
+
+    let rows = outcomes
+        .iter()
+        .map(|outcome| RunRow {
+            line: outcome.line,
+            command: outcome.command.clone(),
+            result: outcome.result.to_string(),
+            time: outcome
+                .millis
+                .map(|millis| format!("{}ms", millis))
+                .unwrap_or_else(|| "-".to_string()),
+            error: outcome.error.clone().unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+    println!();
+    display_table(&style_table(&mut Table::new(rows), Some(2), false));
+
+    let count = |result: &str| outcomes.iter().filter(|o| o.result == result).count();
+    println!(
+        "{} ok, {} failed, {} skipped",
+        count("ok"),
+        count("failed"),
+        count("skipped")
+    );
+    if count("failed") > 0 {
+        return Err(format!("{} of {} commands failed", count("failed"), outcomes.len()).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,312 @@
+use artisan_middleware::{
+    aggregator::{BilledUsageSummary, BillingCosts},
+    dusa_collection_utils::{core::logger::LogLevel, log},
+    portal::{
+        ApiResponse, CommandResponse, InstanceLogResponse, RunnerDetails, RunnerHealth,
+        RunnerSummary,
+    },
+    timestamp::format_unix_timestamp,
+};
+use owo_colors::OwoColorize;
+use reqwest::Client;
+use tabled::Table;
+
+use crate::{
+    audit::audit,
+    cache::cached_get,
+    cli::ListArgs,
+    defs::{BillingEntry, RunnerInstanceRow, RunnerRow},
+    file::get_token,
+    formatting::{
+        display_rows, display_table, format_bytes, format_duration, format_relative, join_ids,
+        print_logs, strip_ansi_codes, style_table,
+    },
+    get_base_url,
+    http::{api_client, send},
+};
+
+pub async fn show_logs(lines: u64, instance_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    let mut line_array = Vec::new();
+
+    let response = cached_get(&client, &token, &format!("logs/{}/{}", instance_id, lines)).await?;
+
+    if response.status().is_success() {
+        let api_response: ApiResponse<InstanceLogResponse> = response.json()?;
+        if let Some(log_data) = api_response.data {
+            let sorted = log_data.lines;
+            let mut e = 1;
+
+            log!(LogLevel::Info, "Runner: {}", log_data.runner_id);
+            log!(LogLevel::Info, "Instance: {}", log_data.instance_id);
+            sorted.iter().for_each(|entry| {
+                let line = format!(
+                    "[{:03} of {:03}] @ {} -> {}",
+                    e, lines, entry.timestamp, entry.message
+                );
+                line_array.push(line);
+                e += 1;
+            });
+
+            print_logs(line_array, format!("{} Logs ('q' to quit)", instance_id))?;
+        }
+    } else {
+        return Err(format!("Failed to list logs: {}", response.text()).into());
+    }
+
+    Ok(())
+}
+
+pub async fn calculate_billing(runner_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    let response = cached_get(&client, &token, &format!("usage/group/{}", runner_id)).await?;
+
+    if response.status().is_success() {
+        let api_response: ApiResponse<BilledUsageSummary> = response.json()?;
+        if let Some(summary) = api_response.data {
+            match send(
+                client
+                    .post(format!(
+                        "{}billing/calculate?instances={}",
+                        get_base_url(),
+                        summary.instances
+                    ))
+                    .json(&summary),
+            )
+            .await
+            {
+                Ok(response) => {
+                    let text = response.text().await?;
+                    // println!("Raw response body: {:?}", text);
+
+                    // Optional: try parsing only if it's not empty
+                    if text.trim().is_empty() {
+                        return Err("Empty billing response from the server".into());
+                    }
+                    let api_response: ApiResponse<BillingCosts> = serde_json::from_str(&text)?;
+
+                    match api_response.data {
+                        Some(data) => {
+                            let rows = vec![
+                                BillingEntry {
+                                    label: "RAM Usage".to_string(),
+                                    value: format!("${:.2}", data.ram_cost),
+                                },
+                                BillingEntry {
+                                    label: "CPU Usage".to_string(),
+                                    value: format!("${:.2}", data.cpu_cost),
+                                },
+                                BillingEntry {
+                                    label: "Bandwidth".to_string(),
+                                    value: format!("${:.2}", data.bandwidth_cost),
+                                },
+                                BillingEntry {
+                                    label: "Base Hosting".to_string(),
+                                    value: format!("${:.2}", (data.instances * 5)),
+                                },
+                                // BillingEntry { label: "Total".to_string(), value: format!("{}", format!("${:.2}", data.total_cost).bold().green()) },
+                            ];
+
+                            let mut table = Table::new(rows);
+                            table = style_table(&mut table, None, true);
+                            display_table(&table);
+                            log!(LogLevel::Info, "Total: ${:.2}", data.total_cost);
+                        }
+                        None => {
+                            return Err(format!(
+                                "Invalid billing response: {}: {:?}",
+                                api_response.status, api_response.errors
+                            )
+                            .into());
+                        }
+                    }
+                }
+                Err(err) => return Err(format!("Failed to get bill data: {}", err).into()),
+            }
+        } else {
+            log!(LogLevel::Warn, "The server didn't give us usage data.");
+        }
+    } else {
+        return Err(format!("Failed to fetch usage: {}", response.text()).into());
+    }
+
+    Ok(())
+}
+
+pub async fn list_runners(list: &ListArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    let response = cached_get(&client, &token, "runners").await?;
+
+    if response.status().is_success() {
+        let api_response: ApiResponse<Vec<RunnerSummary>> = response.json()?;
+        if let Some(runners) = api_response.data {
+            if runners.is_empty() {
+                println!("{}", "No runners found.".yellow());
+            } else {
+                let rows = runners
+                    .into_iter()
+                    .map(|r| RunnerRow {
+                        name: strip_ansi_codes(r.name.replace("ais_", "").trim_ascii()),
+                        status: strip_ansi_codes(r.status.to_string().trim_ascii()),
+                        uptime: format_duration(r.uptime.unwrap_or(0)),
+                        instances: strip_ansi_codes(r.nodes.len().to_string().trim_ascii()),
+                        nodes: join_ids(&r.nodes),
+                    })
+                    .collect::<Vec<_>>();
+
+                display_rows(rows, list, true)?;
+            }
+        } else {
+            log!(LogLevel::Error, "No runners found");
+        }
+    } else {
+        return Err(format!("Failed to list runners: {}", response.text()).into());
+    }
+
+    Ok(())
+}
+
+pub async fn get_runner_details(
+    runner_id: &str,
+    list: &ListArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    let response = cached_get(&client, &token, &format!("runner/{}", runner_id)).await?;
+
+    if response.status().is_success() {
+        let api_response: ApiResponse<Vec<RunnerDetails>> = response.json()?;
+        if let Some(runners) = api_response.data {
+            // log!(LogLevel::Info, "Information on {} runner group", runner_id);
+
+            let rows = runners
+                .into_iter()
+                .map(|runner| {
+                    // No health record means no check yet, not one just now
+                    let last_check = runner
+                        .health
+                        .as_ref()
+                        .map_or("-".to_string(), |health| format_relative(health.last_check));
+                    let health = runner.health.unwrap_or_else(|| RunnerHealth {
+                        uptime: 0,
+                        last_check: 0,
+                        cpu_usage: "-".into(),
+                        ram_usage: "-".into(),
+                        tx_bytes: 0,
+                        rx_bytes: 0,
+                    });
+
+                    let log_len = runner.logs.as_ref().map_or(0, |logs| logs.recent.len());
+
+                    RunnerInstanceRow {
+                        id: runner.id.to_string(),
+                        status: strip_ansi_codes(&runner.status.to_string()),
+                        uptime: format_duration(health.uptime),
+                        cpu: health.cpu_usage.to_string(),
+                        ram: health.ram_usage.to_string(),
+                        rx: format_bytes(health.rx_bytes),
+                        tx: format_bytes(health.tx_bytes),
+                        log_len: log_len.to_string(),
+                        last_check,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            display_rows(rows, list, false)?;
+        } else {
+            return Err(format!("Runner {} not found", runner_id).into());
+        }
+    } else {
+        return Err(format!("Failed to get runner details: {}", response.text()).into());
+    }
+
+    Ok(())
+}
+
+/// Queues `command` on a runner and records it in the audit log, API errors
+/// and empty answers come back as `Err`.
+pub async fn queue_control(
+    client: &Client,
+    token: &str,
+    runner_id: &str,
+    command: &str,
+) -> Result<CommandResponse, Box<dyn std::error::Error>> {
+    let result = send_control(client, token, runner_id, command).await;
+    let (queued_at, errors) = match &result {
+        Ok(response) => (Some(response.queued_at), Vec::new()),
+        Err(err) => (None, vec![err.to_string()]),
+    };
+    audit(
+        client,
+        token,
+        &format!("control {}", command),
+        runner_id,
+        queued_at,
+        errors,
+    )
+    .await;
+    result
+}
+
+async fn send_control(
+    client: &Client,
+    token: &str,
+    runner_id: &str,
+    command: &str,
+) -> Result<CommandResponse, Box<dyn std::error::Error>> {
+    let response = cached_get(client, token, &format!("control/{}/{}", runner_id, command)).await?;
+
+    if !response.status().is_success() {
+        return Err(format!("Failed to control runner: {}", response.text()).into());
+    }
+
+    let api_response: ApiResponse<CommandResponse> = response.json()?;
+    if !api_response.errors.is_empty() {
+        let errors = api_response
+            .errors
+            .iter()
+            .map(|err| format!("{:?}: {}", err.code, err.message))
+            .collect::<Vec<_>>();
+        return Err(errors.join(", ").into());
+    }
+
+    match api_response.data {
+        Some(data) => Ok(data),
+        None => Err(format!(
+            "Something may have went wrong, here's some json: {:?}",
+            api_response
+        )
+        .into()),
+    }
+}
+
+pub async fn control_runner(
+    runner_id: &str,
+    command: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    let data = queue_control(&client, &token, runner_id, command).await?;
+    let name = if data.runner_id == "general" {
+        format!("{} runner group", runner_id)
+    } else {
+        runner_id.to_string()
+    };
+    log!(
+        LogLevel::Info,
+        "Executed: {} on {} @ {}",
+        data.command,
+        name,
+        format_unix_timestamp(data.queued_at)
+    );
+
+    Ok(())
+}
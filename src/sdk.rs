@@ -0,0 +1,316 @@
+//! Typed async access to the portal API for our other Rust tools.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use artisan_cli::sdk::ArtisanClient;
+//!
+//! let client = ArtisanClient::new()?;
+//! client.login("ops@example.com", "password").await?;
+//! for runner in client.runners().await? {
+//!     println!("{} is {:?}", runner.name, runner.status);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{env, sync::Mutex};
+
+use artisan_middleware::dusa_collection_utils::core::functions::current_timestamp;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+pub use artisan_middleware::{
+    aggregator::{BilledUsageSummary, BillingCosts},
+    portal::{
+        ApiResponse, CommandResponse, InstanceLogResponse, NodeDetails, NodeInfo, RunnerDetails,
+        RunnerSummary,
+    },
+};
+
+use crate::{
+    file::get_token,
+    get_base_url,
+    http::{api_client, send, send_get},
+};
+
+/// An access token and the refresh token that renews it.
+#[derive(Clone)]
+pub struct Session {
+    pub token: String,
+    pub refresh_token: String,
+}
+
+/// Reads the `exp` claim of a JWT without verifying it, `None` when the
+/// token doesn't look like one.
+pub fn token_expiry(token: &str) -> Option<u64> {
+    let claims = token.split('.').nth(1)?;
+    let claims = base64::decode_config(claims, base64::URL_SAFE).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&claims).ok()?;
+    claims.get("exp")?.as_u64()
+}
+
+pub fn token_expired(token: &str) -> bool {
+    token_expiry(token).is_some_and(|exp| exp < current_timestamp())
+}
+
+/// POSTs `auth/login` and returns the new session.
+pub async fn request_login(
+    client: &Client,
+    base_url: &str,
+    email: &str,
+    password: &str,
+) -> Result<Session, Box<dyn std::error::Error>> {
+    let response = send(
+        client
+            .post(format!("{}auth/login", base_url))
+            .json(&json!({ "email": email, "password": password })),
+    )
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(format!("Login failed: {}", response.status()).into());
+    }
+
+    let json: serde_json::Value = response.json().await?;
+    match (
+        json.get("auth").and_then(|t| t.as_str()),
+        json.get("refresh").and_then(|t| t.as_str()),
+    ) {
+        (Some(token), Some(refresh_token)) => Ok(Session {
+            token: token.to_string(),
+            refresh_token: refresh_token.to_string(),
+        }),
+        _ => Err("Failed to parse both refresh and auth token".into()),
+    }
+}
+
+/// POSTs `auth/refresh` and returns the renewed access token.
+pub async fn request_refresh(
+    client: &Client,
+    base_url: &str,
+    session: &Session,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let response = send(
+        client
+            .post(format!("{}auth/refresh", base_url))
+            .json(&json!({
+                "expired_token": session.token,
+                "refresh_token": session.refresh_token
+            })),
+    )
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(format!("Failed to refresh session: {}", response.status()).into());
+    }
+
+    let json: serde_json::Value = response.json().await?;
+    json.get("auth")
+        .and_then(|t| t.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| "Refresh response had no token".into())
+}
+
+/// A portal client that keeps its own session and refreshes it when the
+/// access token runs out. Requests go through the same timeouts, proxy, TLS
+/// and retry policy as the CLI, see [`crate::http`].
+pub struct ArtisanClient {
+    http: Client,
+    base_url: String,
+    session: Mutex<Option<Session>>,
+}
+
+impl ArtisanClient {
+    /// Talks to `ARTISAN_API_URL`, or the production portal when unset.
+    pub fn new() -> Result<ArtisanClient, Box<dyn std::error::Error>> {
+        ArtisanClient::with_base_url(get_base_url())
+    }
+
+    pub fn with_base_url(base_url: &str) -> Result<ArtisanClient, Box<dyn std::error::Error>> {
+        Ok(ArtisanClient {
+            http: api_client()?,
+            base_url: format!("{}/", base_url.trim_end_matches('/')),
+            session: Mutex::new(None),
+        })
+    }
+
+    /// Swaps in a preconfigured `reqwest` client.
+    pub fn with_http_client(mut self, http: Client) -> ArtisanClient {
+        self.http = http;
+        self
+    }
+
+    pub fn with_session(self, token: &str, refresh_token: &str) -> ArtisanClient {
+        self.set_session(Session {
+            token: token.to_string(),
+            refresh_token: refresh_token.to_string(),
+        });
+        self
+    }
+
+    /// Reuses the session `artisan_cli auth login` saved in
+    /// `~/.artisan_cli/.env`, refreshing it there like the CLI would.
+    pub async fn from_cli_session() -> Result<ArtisanClient, Box<dyn std::error::Error>> {
+        if let Some(home_dir) = dirs::home_dir() {
+            dotenv::from_path(home_dir.join(".artisan_cli").join(".env")).ok();
+        }
+        let token = get_token().await?;
+        let refresh_token = env::var("REFRESH_TOKEN").unwrap_or_default();
+        Ok(ArtisanClient::new()?.with_session(&token, &refresh_token))
+    }
+
+    fn set_session(&self, session: Session) {
+        *self.session.lock().unwrap_or_else(|err| err.into_inner()) = Some(session);
+    }
+
+    pub fn session(&self) -> Option<Session> {
+        self.session
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    pub async fn login(
+        &self,
+        email: &str,
+        password: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let session = request_login(&self.http, &self.base_url, email, password).await?;
+        self.set_session(session);
+        Ok(())
+    }
+
+    /// The current access token, refreshed first if it has expired.
+    pub async fn token(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let mut session = self.session().ok_or("Not logged in")?;
+        if token_expired(&session.token) {
+            session.token = request_refresh(&self.http, &self.base_url, &session).await?;
+            self.set_session(session.clone());
+        }
+        Ok(session.token)
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<Option<T>, Box<dyn std::error::Error>> {
+        let token = self.token().await?;
+        let request = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .bearer_auth(token);
+        let response = send_get(request, path).await?;
+
+        if !response.status().is_success() {
+            return Err(format!(
+                "{} returned {}: {}",
+                path,
+                response.status(),
+                response.text().await.unwrap_or_default()
+            )
+            .into());
+        }
+
+        let api_response: ApiResponse<T> = response.json().await?;
+        if !api_response.errors.is_empty() {
+            let messages = api_response
+                .errors
+                .iter()
+                .map(|err| format!("{:?}: {}", err.code, err.message))
+                .collect::<Vec<_>>();
+            return Err(messages.join(", ").into());
+        }
+        Ok(api_response.data)
+    }
+
+    async fn get_one<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        self.get(path)
+            .await?
+            .ok_or_else(|| format!("{} returned no data", path).into())
+    }
+
+    pub async fn nodes(&self) -> Result<Vec<NodeInfo>, Box<dyn std::error::Error>> {
+        Ok(self.get("nodes").await?.unwrap_or_default())
+    }
+
+    pub async fn node(&self, node_id: &str) -> Result<NodeDetails, Box<dyn std::error::Error>> {
+        self.get_one(&format!("node/{}", node_id)).await
+    }
+
+    pub async fn runners(&self) -> Result<Vec<RunnerSummary>, Box<dyn std::error::Error>> {
+        Ok(self.get("runners").await?.unwrap_or_default())
+    }
+
+    /// Every instance of the runner group `runner_id`.
+    pub async fn runner(
+        &self,
+        runner_id: &str,
+    ) -> Result<Vec<RunnerDetails>, Box<dyn std::error::Error>> {
+        Ok(self
+            .get(&format!("runner/{}", runner_id))
+            .await?
+            .unwrap_or_default())
+    }
+
+    pub async fn runner_usage(
+        &self,
+        runner_id: &str,
+    ) -> Result<BilledUsageSummary, Box<dyn std::error::Error>> {
+        self.get_one(&format!("usage/group/{}", runner_id)).await
+    }
+
+    pub async fn instance_usage(
+        &self,
+        instance_id: &str,
+    ) -> Result<BilledUsageSummary, Box<dyn std::error::Error>> {
+        self.get_one(&format!("usage/single/{}", instance_id)).await
+    }
+
+    pub async fn logs(
+        &self,
+        instance_id: &str,
+        lines: u64,
+    ) -> Result<InstanceLogResponse, Box<dyn std::error::Error>> {
+        self.get_one(&format!("logs/{}/{}", instance_id, lines))
+            .await
+    }
+
+    /// Queues `command` (start, stop, restart, ...) on a runner. Not retried
+    /// unless the CLI's `--retry-unsafe` policy is in effect.
+    pub async fn control(
+        &self,
+        runner_id: &str,
+        command: &str,
+    ) -> Result<CommandResponse, Box<dyn std::error::Error>> {
+        self.get_one(&format!("control/{}/{}", runner_id, command))
+            .await
+    }
+
+    pub async fn calculate_billing(
+        &self,
+        summary: &BilledUsageSummary,
+    ) -> Result<BillingCosts, Box<dyn std::error::Error>> {
+        let response = send(
+            self.http
+                .post(format!(
+                    "{}billing/calculate?instances={}",
+                    self.base_url, summary.instances
+                ))
+                .json(summary),
+        )
+        .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("billing/calculate returned {}", response.status()).into());
+        }
+
+        let api_response: ApiResponse<BillingCosts> = response.json().await?;
+        api_response
+            .data
+            .ok_or_else(|| "billing/calculate returned no data".into())
+    }
+}
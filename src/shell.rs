@@ -0,0 +1,461 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+};
+
+use artisan_middleware::{
+    dusa_collection_utils::{core::logger::LogLevel, log},
+    portal::{NodeInfo, RunnerSummary},
+};
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use crossterm::{
+    cursor::MoveToColumn,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    terminal::{self, Clear, ClearType},
+};
+
+use crate::{
+    alias::expand_aliases,
+    cache::last_seen,
+    cli::TopLevelCommand,
+    commands::execute,
+    file::app_dir,
+    formatting::{heading, strip_ansi_codes},
+    run::{script_words, ScriptLine},
+};
+
+const HISTORY_LIMIT: usize = 1000;
+const BUILTINS: [&str; 4] = ["use", "help", "exit", "quit"];
+
+/// Set by `use runner X` / `use node X`, filled into later commands that
+/// leave that ID out.
+#[derive(Default)]
+struct Context {
+    runner: Option<String>,
+    node: Option<String>,
+}
+
+impl Context {
+    fn prompt(&self) -> String {
+        let mut scope = Vec::new();
+        if let Some(runner) = &self.runner {
+            scope.push(format!("runner:{}", runner));
+        }
+        if let Some(node) = &self.node {
+            scope.push(format!("node:{}", node));
+        }
+        if scope.is_empty() {
+            format!("{} ", heading("artisan>"))
+        } else {
+            format!("{} ", heading(&format!("artisan[{}]>", scope.join(" "))))
+        }
+    }
+
+    fn set(&mut self, args: &[String]) {
+        match args {
+            [kind, id] if kind == "runner" => self.runner = Some(id.clone()),
+            [kind, id] if kind == "node" => self.node = Some(id.clone()),
+            [none] if none == "none" => *self = Context::default(),
+            [] => println!(
+                "runner: {}, node: {}",
+                self.runner.as_deref().unwrap_or("-"),
+                self.node.as_deref().unwrap_or("-")
+            ),
+            _ => log!(
+                LogLevel::Warn,
+                "Usage: use runner <RUNNER_ID> | use node <NODE_ID> | use none"
+            ),
+        }
+    }
+}
+
+fn history_file() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(app_dir()?.join("shell_history"))
+}
+
+/// The last `HISTORY_LIMIT` lines, the file is trimmed to match so it
+/// doesn't grow forever.
+fn load_history() -> Vec<String> {
+    let Ok(path) = history_file() else {
+        return Vec::new();
+    };
+    let mut history = fs::read_to_string(&path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if history.len() > HISTORY_LIMIT {
+        history.drain(..history.len() - HISTORY_LIMIT);
+        let _ = fs::write(&path, history.join("\n") + "\n");
+    }
+    history
+}
+
+fn append_history(line: &str) {
+    let written = history_file().and_then(|path| {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    });
+    if let Err(err) = written {
+        log!(LogLevel::Debug, "Failed to save shell history: {}", err);
+    }
+}
+
+fn seen_runners() -> Vec<String> {
+    last_seen::<Vec<RunnerSummary>>("runners")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|runner| runner.name.to_string())
+        .collect()
+}
+
+fn seen_nodes() -> Vec<String> {
+    last_seen::<Vec<NodeInfo>>("nodes")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|node| node.identity.id.to_string())
+        .collect()
+}
+
+/// Where the word under the cursor starts in `line` and what it could be:
+/// builtins and subcommands by position, flags after a `-`, otherwise the
+/// runner names and node IDs of the last `runner list` / `node list`.
+fn complete(line: &str) -> (usize, Vec<String>) {
+    let start = line
+        .rfind(char::is_whitespace)
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let prefix = &line[start..];
+    let mut words = line[..start].split_whitespace().collect::<Vec<_>>();
+    if words.first() == Some(&"artisan_cli") {
+        words.remove(0);
+    }
+
+    let mut command = ScriptLine::command();
+    let candidates = match words.as_slice() {
+        [] => BUILTINS
+            .iter()
+            .map(|builtin| builtin.to_string())
+            .chain(
+                command
+                    .get_subcommands()
+                    .map(|sub| sub.get_name().to_string()),
+            )
+            .collect(),
+        ["use"] => vec!["runner".to_string(), "node".to_string(), "none".to_string()],
+        ["use", "runner"] => seen_runners(),
+        ["use", "node"] => seen_nodes(),
+        ["use", ..] => Vec::new(),
+        _ => {
+            for word in &words {
+                match command.find_subcommand(word) {
+                    Some(sub) => command = sub.clone(),
+                    None => break,
+                }
+            }
+            if prefix.starts_with('-') {
+                command
+                    .get_arguments()
+                    .filter_map(|arg| arg.get_long())
+                    .map(|long| format!("--{}", long))
+                    .collect()
+            } else if command.has_subcommands() {
+                command
+                    .get_subcommands()
+                    .map(|sub| sub.get_name().to_string())
+                    .collect()
+            } else if words[0] == "node" || words.last() == Some(&"--node") {
+                seen_nodes()
+            } else {
+                seen_runners()
+            }
+        }
+    };
+
+    let mut candidates = candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(prefix))
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates.dedup();
+    (start, candidates)
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let mut prefix = words[0].clone();
+    for word in &words[1..] {
+        let shared = prefix
+            .chars()
+            .zip(word.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        prefix.truncate(shared);
+    }
+    prefix
+}
+
+/// Puts the terminal back to normal however `read_line` returns.
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn redraw(prompt: &str, buffer: &[char], cursor: usize) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    queue!(stdout, MoveToColumn(0), Clear(ClearType::CurrentLine))?;
+    write!(stdout, "{}{}", prompt, buffer.iter().collect::<String>())?;
+    let column = strip_ansi_codes(prompt).chars().count() + cursor;
+    queue!(stdout, MoveToColumn(column as u16))?;
+    stdout.flush()
+}
+
+/// Reads a line with cursor movement, up/down through `history` and tab
+/// completion. `None` on Ctrl-D at an empty prompt.
+fn read_line(prompt: &str, history: &[String]) -> io::Result<Option<String>> {
+    let _raw = RawMode::enable()?;
+    let mut buffer = Vec::<char>::new();
+    let mut cursor = 0;
+    // history.len() is the line being typed, kept in `draft` while browsing
+    let mut recall = history.len();
+    let mut draft = Vec::new();
+    redraw(prompt, &buffer, cursor)?;
+
+    loop {
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind,
+            ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+        if kind == KeyEventKind::Release {
+            continue;
+        }
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+
+        match code {
+            KeyCode::Enter => {
+                print!("\r\n");
+                io::stdout().flush()?;
+                return Ok(Some(buffer.into_iter().collect()));
+            }
+            KeyCode::Char('d') if ctrl && buffer.is_empty() => {
+                print!("\r\n");
+                return Ok(None);
+            }
+            KeyCode::Char('c') if ctrl => {
+                print!("^C\r\n");
+                buffer.clear();
+                cursor = 0;
+                recall = history.len();
+            }
+            KeyCode::Char('a') if ctrl => cursor = 0,
+            KeyCode::Char('e') if ctrl => cursor = buffer.len(),
+            KeyCode::Char('u') if ctrl => {
+                buffer.drain(..cursor);
+                cursor = 0;
+            }
+            KeyCode::Char(ch) if !ctrl => {
+                buffer.insert(cursor, ch);
+                cursor += 1;
+            }
+            KeyCode::Backspace if cursor > 0 => {
+                cursor -= 1;
+                buffer.remove(cursor);
+            }
+            KeyCode::Delete if cursor < buffer.len() => {
+                buffer.remove(cursor);
+            }
+            KeyCode::Left if cursor > 0 => cursor -= 1,
+            KeyCode::Right if cursor < buffer.len() => cursor += 1,
+            KeyCode::Home => cursor = 0,
+            KeyCode::End => cursor = buffer.len(),
+            KeyCode::Up if recall > 0 => {
+                if recall == history.len() {
+                    draft = buffer;
+                }
+                recall -= 1;
+                buffer = history[recall].chars().collect();
+                cursor = buffer.len();
+            }
+            KeyCode::Down if recall < history.len() => {
+                recall += 1;
+                buffer = if recall == history.len() {
+                    std::mem::take(&mut draft)
+                } else {
+                    history[recall].chars().collect()
+                };
+                cursor = buffer.len();
+            }
+            KeyCode::Tab => {
+                let before = buffer[..cursor].iter().collect::<String>();
+                let (start, candidates) = complete(&before);
+                let start = before[..start].chars().count();
+                let insert = match candidates.as_slice() {
+                    [] => None,
+                    [only] => Some(format!("{} ", only)),
+                    many => {
+                        let common = common_prefix(many);
+                        if common.chars().count() > cursor - start {
+                            Some(common)
+                        } else {
+                            print!("\r\n{}\r\n", many.join("  "));
+                            None
+                        }
+                    }
+                };
+                if let Some(insert) = insert {
+                    let insert = insert.chars().collect::<Vec<_>>();
+                    let inserted = insert.len();
+                    buffer.splice(start..cursor, insert);
+                    cursor = start + inserted;
+                }
+            }
+            _ => {}
+        }
+        redraw(prompt, &buffer, cursor)?;
+    }
+}
+
+/// Parses a shell line, filling in the `use`d runner or node when the
+/// command leaves its ID out.
+fn parse(words: &[String], context: &Context) -> Result<TopLevelCommand, clap::Error> {
+    let mut words = words.to_vec();
+    if words[0] == "tree" && !words.iter().any(|w| w == "--runner" || w == "--node") {
+        if let Some(runner) = &context.runner {
+            words.extend(["--runner".to_string(), runner.clone()]);
+        }
+        if let Some(node) = &context.node {
+            words.extend(["--node".to_string(), node.clone()]);
+        }
+    }
+
+    let err = match ScriptLine::try_parse_from(&words) {
+        Ok(line) => return Ok(line.command),
+        Err(err) => err,
+    };
+    let id = match words[0].as_str() {
+        "runner" => context.runner.as_ref(),
+        "node" => context.node.as_ref(),
+        _ => None,
+    };
+    match id {
+        Some(id) if err.kind() == ErrorKind::MissingRequiredArgument && !words.contains(id) => {
+            // IDs are the first positional, right after the subcommand
+            words.insert(2.min(words.len()), id.clone());
+            ScriptLine::try_parse_from(&words)
+                .map(|line| line.command)
+                .map_err(|_| err)
+        }
+        _ => Err(err),
+    }
+}
+
+fn print_help() {
+    println!("{}", ScriptLine::command().render_help());
+    println!("{}", heading("Shell:"));
+    println!("  use runner <RUNNER_ID>  default runner for runner commands and tree");
+    println!("  use node <NODE_ID>      default node for node commands and tree");
+    println!("  use none                clear both");
+    println!("  exit, quit, Ctrl-D      leave the shell");
+}
+
+/// A prompt that takes the same commands as the binary, minus its name.
+/// History lives in `~/.artisan_cli/shell_history`. With stdin piped in it
+/// reads one command per line without prompting.
+pub async fn shell() -> Result<(), Box<dyn std::error::Error>> {
+    let interactive = io::stdin().is_terminal();
+    let mut history = if interactive {
+        load_history()
+    } else {
+        Vec::new()
+    };
+    let mut context = Context::default();
+    if interactive {
+        println!("artisan_cli shell, `help` lists commands, `exit` or Ctrl-D leaves");
+    }
+
+    loop {
+        let line = if interactive {
+            match read_line(&context.prompt(), &history)? {
+                Some(line) => line,
+                None => break,
+            }
+        } else {
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line)? == 0 {
+                break;
+            }
+            line
+        };
+
+        let trimmed = line.trim();
+        if interactive && !trimmed.is_empty() && history.last().map(String::as_str) != Some(trimmed)
+        {
+            history.push(trimmed.to_string());
+            append_history(trimmed);
+        }
+
+        let words = match script_words(&line) {
+            Ok(Some(words)) => words,
+            Ok(None) => continue,
+            Err(err) => {
+                log!(LogLevel::Error, "{}", err);
+                continue;
+            }
+        };
+        match words[0].as_str() {
+            "exit" | "quit" => break,
+            "use" => {
+                context.set(&words[1..]);
+                continue;
+            }
+            "help" if words.len() == 1 => {
+                print_help();
+                continue;
+            }
+            _ => {}
+        }
+
+        let words = match expand_aliases(words, 0) {
+            Ok(words) => words,
+            Err(err) => {
+                log!(LogLevel::Error, "{}", err);
+                continue;
+            }
+        };
+        let command = match parse(&words, &context) {
+            Ok(command) => command,
+            Err(err) => {
+                let _ = err.print();
+                continue;
+            }
+        };
+        if matches!(command, TopLevelCommand::Shell) {
+            log!(LogLevel::Warn, "Already in the shell");
+            continue;
+        }
+        // Boxed since `execute` is what called us
+        if let Err(err) = Box::pin(execute(&command)).await {
+            log!(LogLevel::Error, "{}", err);
+        }
+    }
+
+    Ok(())
+}
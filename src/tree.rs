@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+
+use artisan_middleware::{
+    dusa_collection_utils::{core::logger::LogLevel, log},
+    portal::{NodeInfo, RunnerDetails, RunnerSummary},
+};
+use serde::Serialize;
+
+use crate::{
+    fetch_data,
+    file::get_token,
+    formatting::{color_status, strip_ansi_codes},
+    http::api_client,
+};
+
+#[derive(Serialize, Clone)]
+pub struct TreeNode {
+    pub kind: &'static str,
+    pub id: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+    pub children: Vec<TreeNode>,
+}
+
+pub fn same_runner(a: &str, b: &str) -> bool {
+    a.replace("ais_", "") == b.replace("ais_", "")
+}
+
+fn plural(count: usize, word: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, word)
+    } else {
+        format!("{} {}s", count, word)
+    }
+}
+
+fn collect_ids<'a>(nodes: &'a [TreeNode], kind: &str, ids: &mut HashSet<&'a str>) {
+    for node in nodes {
+        if node.kind == kind {
+            ids.insert(&node.id);
+        }
+        collect_ids(&node.children, kind, ids);
+    }
+}
+
+fn count_kind(nodes: &[TreeNode], kind: &str) -> usize {
+    let mut ids = HashSet::new();
+    collect_ids(nodes, kind, &mut ids);
+    ids.len()
+}
+
+fn render(node: &TreeNode, prefix: &str, last: bool, root: bool, out: &mut String) {
+    let (branch, indent) = match (root, last) {
+        (true, _) => ("", ""),
+        (false, true) => ("└── ", "    "),
+        (false, false) => ("├── ", "│   "),
+    };
+
+    out.push_str(&format!(
+        "{}{}{} {}",
+        prefix,
+        branch,
+        node.id,
+        color_status(&node.status)
+    ));
+    if !node.detail.is_empty() {
+        out.push_str(&format!(" ({})", node.detail));
+    }
+    out.push('\n');
+
+    let child_prefix = format!("{}{}", prefix, indent);
+    for (i, child) in node.children.iter().enumerate() {
+        render(
+            child,
+            &child_prefix,
+            i + 1 == node.children.len(),
+            false,
+            out,
+        );
+    }
+}
+
+/// Prints the fleet as node → runner, or runner → node / instance when
+/// rooted at a runner. Instances carry no node, so they're only listed under
+/// their runner group, never under a node that may not host them.
+pub async fn show_tree(
+    node_filter: Option<&str>,
+    runner_filter: Option<&str>,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    let nodes = fetch_data::<Vec<NodeInfo>>(&client, &token, "nodes")
+        .await?
+        .unwrap_or_default();
+    let runners = fetch_data::<Vec<RunnerSummary>>(&client, &token, "runners")
+        .await?
+        .unwrap_or_default();
+
+    let hosted = |node: &NodeInfo| {
+        node.runners
+            .iter()
+            .map(|runner| runner.to_string())
+            .collect::<Vec<_>>()
+    };
+    let node_id = |node: &NodeInfo| node.identity.id.to_string();
+    let node_wanted = |node: &NodeInfo| node_filter.is_none_or(|id| node_id(node) == id);
+    let runner_wanted = |name: &str| match (runner_filter, node_filter) {
+        (Some(filter), _) => same_runner(filter, name),
+        (None, Some(_)) => nodes
+            .iter()
+            .filter(|node| node_wanted(node))
+            .any(|node| hosted(node).iter().any(|r| same_runner(r, name))),
+        (None, None) => true,
+    };
+
+    // Instances are looked up once per runner group no matter how many nodes host it
+    let mut instances: HashMap<String, Vec<TreeNode>> = HashMap::new();
+    for runner in &runners {
+        let name = runner.name.to_string();
+        if !runner_wanted(&name) {
+            continue;
+        }
+
+        let details =
+            match fetch_data::<Vec<RunnerDetails>>(&client, &token, &format!("runner/{}", name))
+                .await
+            {
+                Ok(details) => details.unwrap_or_default(),
+                Err(err) => {
+                    log!(LogLevel::Warn, "Skipping instances of {}: {}", name, err);
+                    Vec::new()
+                }
+            };
+
+        instances.insert(
+            name,
+            details
+                .into_iter()
+                .map(|instance| TreeNode {
+                    kind: "instance",
+                    id: instance.id.to_string(),
+                    status: strip_ansi_codes(&instance.status.to_string()),
+                    detail: String::new(),
+                    children: Vec::new(),
+                })
+                .collect(),
+        );
+    }
+
+    let runner_tree = |runner: &RunnerSummary, children: Vec<TreeNode>| {
+        let name = runner.name.to_string();
+        TreeNode {
+            kind: "runner",
+            id: name.replace("ais_", ""),
+            status: strip_ansi_codes(&runner.status.to_string()),
+            detail: format!(
+                "{} group-wide",
+                plural(
+                    instances.get(&name).map_or(0, |list| list.len()),
+                    "instance"
+                )
+            ),
+            children,
+        }
+    };
+
+    let roots = if let Some(filter) = runner_filter {
+        runners
+            .iter()
+            .filter(|runner| same_runner(filter, &runner.name.to_string()))
+            .map(|runner| {
+                let name = runner.name.to_string();
+                let mut children = nodes
+                    .iter()
+                    .filter(|node| hosted(node).iter().any(|r| same_runner(r, &name)))
+                    .filter(|node| node_wanted(node))
+                    .map(|node| TreeNode {
+                        kind: "node",
+                        id: node_id(node),
+                        status: strip_ansi_codes(&node.status.to_string()),
+                        detail: node.ip_address.to_string(),
+                        children: Vec::new(),
+                    })
+                    .collect::<Vec<_>>();
+                children.extend(instances.get(&name).cloned().unwrap_or_default());
+                runner_tree(runner, children)
+            })
+            .collect::<Vec<_>>()
+    } else {
+        nodes
+            .iter()
+            .filter(|node| node_wanted(node))
+            .map(|node| {
+                let children = hosted(node)
+                    .iter()
+                    .map(|hosted_name| {
+                        match runners
+                            .iter()
+                            .find(|runner| same_runner(hosted_name, &runner.name.to_string()))
+                        {
+                            Some(runner) => runner_tree(runner, Vec::new()),
+                            None => TreeNode {
+                                kind: "runner",
+                                id: hosted_name.replace("ais_", ""),
+                                status: "Unknown".to_string(),
+                                detail: String::new(),
+                                children: Vec::new(),
+                            },
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                TreeNode {
+                    kind: "node",
+                    id: node_id(node),
+                    status: strip_ansi_codes(&node.status.to_string()),
+                    detail: format!("{}, {}", node.ip_address, plural(children.len(), "runner")),
+                    children,
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&roots)?);
+        return Ok(());
+    }
+
+    if roots.is_empty() {
+        log!(LogLevel::Warn, "Nothing to show for that root.");
+        return Ok(());
+    }
+
+    let mut out = String::new();
+    for root in &roots {
+        render(root, "", true, true, &mut out);
+        out.push('\n');
+    }
+    print!("\n{}", out);
+    println!(
+        "{}, {}, {}",
+        plural(count_kind(&roots, "node"), "node"),
+        plural(count_kind(&roots, "runner"), "runner"),
+        plural(instances.values().map(Vec::len).sum(), "instance")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use artisan_middleware::{
+    aggregator::BilledUsageSummary,
+    dusa_collection_utils::{core::logger::LogLevel, log},
+    portal::{ApiResponse, RunnerDetails},
+};
+use reqwest::Client;
+use tabled::Table;
+
+use crate::{
+    cache::cached_get,
+    cli::UsageColumn,
+    defs::{UsageCompareRow, UsageRow},
+    file::get_token,
+    formatting::{display_table, format_bytes, style_table},
+    http::api_client,
+};
+
+/// Fetches a single usage summary from `usage/single/{id}` or `usage/group/{id}`.
+pub async fn request_usage(
+    client: &Client,
+    token: &str,
+    path: &str,
+) -> Result<Option<BilledUsageSummary>, String> {
+    let response = cached_get(client, token, path)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if response.status().is_success() {
+        let api_response: ApiResponse<BilledUsageSummary> =
+            response.json().map_err(|err| err.to_string())?;
+        Ok(api_response.data)
+    } else {
+        Err(format!("Failed to get usage: {}", response.text()))
+    }
+}
+
+/// Fetches several usage summaries concurrently, results are returned in the
+/// same order as `paths`.
+pub async fn request_usage_many(
+    client: &Client,
+    token: &str,
+    paths: Vec<String>,
+) -> Vec<Result<Option<BilledUsageSummary>, String>> {
+    let handles = paths
+        .into_iter()
+        .map(|path| {
+            let client = client.clone();
+            let token = token.to_string();
+            tokio::spawn(async move { request_usage(&client, &token, &path).await })
+        })
+        .collect::<Vec<_>>();
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await.unwrap_or_else(|err| Err(err.to_string())));
+    }
+    results
+}
+
+pub fn usage_row(summary: &BilledUsageSummary) -> UsageRow {
+    UsageRow {
+        runner_id: summary.runner_id.to_string(),
+        instance_id: summary.instance_id.to_string(),
+        total_cpu: format!("{:.2}", summary.total_cpu),
+        peak_cpu: format!("{:.2}%", summary.peak_cpu),
+        avg_ram: format!("{:.2} MB", summary.avg_memory),
+        peak_ram: format!("{:.2} MB", summary.peak_memory),
+        rx: format_bytes(summary.total_rx),
+        tx: format_bytes(summary.total_tx),
+        samples: summary.total_samples.to_string(),
+    }
+}
+
+async fn show_usage(path: String) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    match request_usage(&client, &token, &path).await {
+        Ok(Some(summary)) => {
+            let mut table = Table::new(vec![usage_row(&summary)]);
+            table = style_table(&mut table, Some(1), true);
+            display_table(&table);
+        }
+        Ok(None) => log!(LogLevel::Warn, "No usage summary found."),
+        Err(err) => log!(LogLevel::Error, "{}", err),
+    }
+
+    Ok(())
+}
+
+pub async fn get_instance_usage(instance_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    show_usage(format!("usage/single/{}", instance_id)).await
+}
+
+pub async fn get_runner_usage(runner_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    show_usage(format!("usage/group/{}", runner_id)).await
+}
+
+/// Expands a runner group into its instances and shows the usage of each one,
+/// followed by a totals row.
+pub async fn get_runner_usage_per_instance(
+    runner_id: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    let response = cached_get(&client, &token, &format!("runner/{}", runner_id)).await?;
+
+    if !response.status().is_success() {
+        log!(
+            LogLevel::Error,
+            "Failed to get runner details: {}",
+            response.text()
+        );
+        return Ok(());
+    }
+
+    let api_response: ApiResponse<Vec<RunnerDetails>> = response.json()?;
+    let instance_ids = match api_response.data {
+        Some(runners) if !runners.is_empty() => runners
+            .into_iter()
+            .map(|runner| runner.id.to_string())
+            .collect::<Vec<_>>(),
+        _ => {
+            log!(LogLevel::Error, "Runner not found.");
+            return Ok(());
+        }
+    };
+
+    let paths = instance_ids
+        .iter()
+        .map(|id| format!("usage/single/{}", id))
+        .collect::<Vec<_>>();
+
+    let mut summaries = Vec::new();
+    for (id, result) in instance_ids
+        .iter()
+        .zip(request_usage_many(&client, &token, paths).await)
+    {
+        match result {
+            Ok(Some(summary)) => summaries.push(summary),
+            Ok(None) => log!(LogLevel::Warn, "No usage summary found for {}.", id),
+            Err(err) => log!(LogLevel::Error, "{}: {}", id, err),
+        }
+    }
+
+    if summaries.is_empty() {
+        log!(LogLevel::Warn, "No usage summary found.");
+        return Ok(());
+    }
+
+    let mut rows = summaries.iter().map(usage_row).collect::<Vec<_>>();
+    rows.push(usage_totals_row(runner_id, &summaries));
+
+    let mut table = Table::new(rows);
+    table = style_table(&mut table, Some(1), true);
+    display_table(&table);
+
+    Ok(())
+}
+
+// Peak CPU is the highest single instance, everything else is summed
+fn usage_totals_row(runner_id: &str, summaries: &[BilledUsageSummary]) -> UsageRow {
+    let sum = |f: fn(&BilledUsageSummary) -> f64| summaries.iter().map(f).sum::<f64>();
+    let peak_cpu = summaries
+        .iter()
+        .map(|summary| f64::from(summary.peak_cpu))
+        .fold(0.0, f64::max);
+
+    UsageRow {
+        runner_id: runner_id.to_string(),
+        instance_id: format!("Total ({} instances)", summaries.len()),
+        total_cpu: format!("{:.2}", sum(|s| f64::from(s.total_cpu))),
+        peak_cpu: format!("{:.2}%", peak_cpu),
+        avg_ram: format!("{:.2} MB", sum(|s| s.avg_memory)),
+        peak_ram: format!("{:.2} MB", sum(|s| s.peak_memory)),
+        rx: format_bytes(summaries.iter().map(|s| s.total_rx).sum()),
+        tx: format_bytes(summaries.iter().map(|s| s.total_tx).sum()),
+        samples: summaries
+            .iter()
+            .map(|s| s.total_samples)
+            .sum::<u64>()
+            .to_string(),
+    }
+}
+
+// Order matches the metric columns of `UsageCompareRow`
+const METRIC_COUNT: usize = 7;
+
+fn usage_metrics(summary: &BilledUsageSummary) -> [f64; METRIC_COUNT] {
+    [
+        f64::from(summary.total_cpu),
+        f64::from(summary.peak_cpu),
+        summary.avg_memory,
+        summary.peak_memory,
+        summary.total_rx as f64,
+        summary.total_tx as f64,
+        summary.total_samples as f64,
+    ]
+}
+
+fn metric_index(column: UsageColumn) -> Option<usize> {
+    match column {
+        UsageColumn::Id => None,
+        UsageColumn::TotalCpu => Some(0),
+        UsageColumn::PeakCpu => Some(1),
+        UsageColumn::AvgRam => Some(2),
+        UsageColumn::PeakRam => Some(3),
+        UsageColumn::Rx => Some(4),
+        UsageColumn::Tx => Some(5),
+        UsageColumn::Samples => Some(6),
+    }
+}
+
+fn render_metric(index: usize, value: f64) -> String {
+    match index {
+        0 => format!("{:.2}", value),
+        1 => format!("{:.2}%", value),
+        2 | 3 => format!("{:.2} MB", value),
+        4 | 5 => format_bytes(value as u64),
+        _ => format!("{}", value as u64),
+    }
+}
+
+/// Renders `value` with its delta and percentage change against the baseline.
+fn compare_cell(index: usize, value: f64, baseline: f64) -> String {
+    let delta = value - baseline;
+    let sign = if delta < 0.0 { "-" } else { "+" };
+    let percent = if baseline == 0.0 {
+        "n/a".to_string()
+    } else {
+        format!("{:+.1}%", delta / baseline * 100.0)
+    };
+
+    format!(
+        "{} ({}{}, {})",
+        render_metric(index, value),
+        sign,
+        render_metric(index, delta.abs()),
+        percent
+    )
+}
+
+/// Shows several runners (or instances) side by side. The first ID given is
+/// the baseline every other row is compared against.
+pub async fn compare_usage(
+    ids: &[String],
+    instances: bool,
+    sort: UsageColumn,
+    desc: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = api_client()?;
+    let token = get_token().await?;
+
+    let scope = if instances { "single" } else { "group" };
+    let paths = ids
+        .iter()
+        .map(|id| format!("usage/{}/{}", scope, id))
+        .collect::<Vec<_>>();
+
+    let mut entries = Vec::new();
+    for (id, result) in ids
+        .iter()
+        .zip(request_usage_many(&client, &token, paths).await)
+    {
+        match result {
+            Ok(Some(summary)) => entries.push((id.clone(), usage_metrics(&summary), false)),
+            Ok(None) => log!(LogLevel::Warn, "No usage summary found for {}.", id),
+            Err(err) => log!(LogLevel::Error, "{}: {}", id, err),
+        }
+    }
+
+    if entries.len() < 2 {
+        log!(
+            LogLevel::Warn,
+            "Need usage for at least two IDs to compare, got {}.",
+            entries.len()
+        );
+        return Ok(());
+    }
+
+    // Flagged rather than matched by ID, the same ID may be given twice
+    let baseline_id = entries[0].0.clone();
+    let baseline = entries[0].1;
+    entries[0].2 = true;
+
+    match metric_index(sort) {
+        Some(index) => entries.sort_by(|a, b| a.1[index].total_cmp(&b.1[index])),
+        None => entries.sort_by(|a, b| a.0.cmp(&b.0)),
+    }
+    if desc {
+        entries.reverse();
+    }
+
+    let rows = entries
+        .into_iter()
+        .map(|(id, metrics, is_baseline)| {
+            let cell = |index: usize| {
+                if is_baseline {
+                    render_metric(index, metrics[index])
+                } else {
+                    compare_cell(index, metrics[index], baseline[index])
+                }
+            };
+
+            UsageCompareRow {
+                id: if is_baseline { format!("{} *", id) } else { id },
+                total_cpu: cell(0),
+                peak_cpu: cell(1),
+                avg_ram: cell(2),
+                peak_ram: cell(3),
+                rx: cell(4),
+                tx: cell(5),
+                samples: cell(6),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut table = Table::new(rows);
+    table = style_table(&mut table, None, false);
+    display_table(&table);
+    log!(
+        LogLevel::Info,
+        "Deltas are relative to {} (marked with *)",
+        baseline_id
+    );
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::{MockPortal, TestHome};
+
+#[test]
+fn aliases_expand_with_positional_arguments() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["alias", "add", "rd", "runner details $1"]);
+    assert!(ok, "{}", out);
+    let (ok, out) = home.run(&["alias", "list"]);
+    assert!(ok && out.contains("runner details $1"), "{}", out);
+
+    // global flags in front of the alias are skipped over
+    let (ok, out) = home.run(&["--timeout", "5", "rd", "ais_web"]);
+    assert!(ok, "{}", out);
+    assert_eq!(portal.hit_count("GET runner/ais_web"), 1);
+
+    let (ok, out) = home.run(&["rd"]);
+    assert!(!ok);
+    assert!(out.contains("needs at least 1 argument"), "{}", out);
+}
+
+#[test]
+fn aliases_cannot_shadow_built_in_commands() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["alias", "add", "node", "runner", "list"]);
+    assert!(!ok);
+    assert!(out.contains("built-in"), "{}", out);
+    assert!(!home.config_path().exists());
+}
+
+#[test]
+fn removed_aliases_stop_working() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    assert!(home.run(&["alias", "add", "nodes", "node", "list"]).0);
+    let (ok, out) = home.run(&["alias", "remove", "nodes"]);
+    assert!(ok, "{}", out);
+
+    let (ok, _) = home.run(&["nodes"]);
+    assert!(!ok);
+    assert_eq!(portal.hit_count("GET nodes"), 0);
+}
@@ -0,0 +1,359 @@
+//! An in process stand-in for the portal API plus a throwaway `$HOME`, so
+//! the tests can run the real binary without touching api.artisanhosting.net.
+
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    net::TcpListener,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Router,
+};
+use serde_json::{json, Value};
+
+pub const EMAIL: &str = "ops@example.com";
+pub const PASSWORD: &str = "hunter2";
+pub const REFRESH_TOKEN: &str = "refresh-1";
+
+const NODES: &str = include_str!("../fixtures/nodes.json");
+const NODE: &str = include_str!("../fixtures/node.json");
+const RUNNERS: &str = include_str!("../fixtures/runners.json");
+const RUNNER: &str = include_str!("../fixtures/runner.json");
+const USAGE: &str = include_str!("../fixtures/usage.json");
+const BILLING: &str = include_str!("../fixtures/billing.json");
+const LOGS: &str = include_str!("../fixtures/logs.json");
+
+static TOKENS_ISSUED: AtomicUsize = AtomicUsize::new(0);
+static HOMES_CREATED: AtomicUsize = AtomicUsize::new(0);
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock before 1970")
+        .as_secs()
+}
+
+/// An unsigned JWT the CLI will accept, only `exp` is ever looked at.
+pub fn jwt(exp: u64) -> String {
+    let serial = TOKENS_ISSUED.fetch_add(1, Ordering::SeqCst);
+    let claims = json!({ "exp": exp, "jti": serial }).to_string();
+    format!(
+        "e30.{}.signature",
+        base64::encode_config(claims, base64::URL_SAFE)
+    )
+}
+
+struct PortalState {
+    hits: Vec<String>,
+    token: String,
+    refresh_allowed: bool,
+    // path -> statuses to answer with before serving it normally
+    failures: HashMap<String, Vec<StatusCode>>,
+}
+
+#[derive(Clone)]
+pub struct MockPortal {
+    state: Arc<Mutex<PortalState>>,
+    url: String,
+}
+
+impl MockPortal {
+    /// Binds a random local port and serves the fixtures from a background
+    /// thread until the test process exits.
+    pub fn start() -> MockPortal {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock portal");
+        listener
+            .set_nonblocking(true)
+            .expect("nonblocking listener");
+        let url = format!("http://{}/v1/", listener.local_addr().unwrap());
+
+        let portal = MockPortal {
+            state: Arc::new(Mutex::new(PortalState {
+                hits: Vec::new(),
+                token: jwt(now() + 3600),
+                refresh_allowed: true,
+                failures: HashMap::new(),
+            })),
+            url,
+        };
+
+        let app = Router::new().fallback(handle).with_state(portal.clone());
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("mock portal runtime");
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                axum::serve(listener, app).await.unwrap();
+            });
+        });
+
+        portal
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The token the portal currently accepts.
+    pub fn token(&self) -> String {
+        self.state.lock().unwrap().token.clone()
+    }
+
+    /// Requests seen so far as `METHOD path`, e.g. `GET runner/ais_web`.
+    pub fn hits(&self) -> Vec<String> {
+        self.state.lock().unwrap().hits.clone()
+    }
+
+    pub fn hit_count(&self, request: &str) -> usize {
+        self.hits().iter().filter(|hit| *hit == request).count()
+    }
+
+    /// Answers the next `statuses.len()` requests for `path` with `statuses`.
+    pub fn fail(&self, path: &str, statuses: &[StatusCode]) {
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(path.to_string(), statuses.iter().rev().copied().collect());
+    }
+
+    pub fn reject_refresh(&self) {
+        self.state.lock().unwrap().refresh_allowed = false;
+    }
+
+    fn issue_token(&self) -> String {
+        let token = jwt(now() + 3600);
+        self.state.lock().unwrap().token = token.clone();
+        token
+    }
+}
+
+fn fixture(body: &str) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+fn payload(data: Value) -> Response {
+    fixture(&json!({ "status": "success", "data": data, "errors": [] }).to_string())
+}
+
+fn not_found(what: &str) -> Response {
+    (StatusCode::NOT_FOUND, format!("{} not found", what)).into_response()
+}
+
+fn known_runner(name: &str) -> bool {
+    matches!(name.trim_start_matches("ais_"), "web" | "worker")
+}
+
+async fn handle(
+    State(portal): State<MockPortal>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let path = uri.path().trim_start_matches("/v1/").to_string();
+    let failure = {
+        let mut state = portal.state.lock().unwrap();
+        state.hits.push(format!("{} {}", method, path));
+        state
+            .failures
+            .get_mut(&path)
+            .and_then(|statuses| statuses.pop())
+    };
+    if let Some(status) = failure {
+        return (status, "injected failure").into_response();
+    }
+
+    let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let segments = path.split('/').collect::<Vec<_>>();
+
+    // Endpoints that don't take a bearer token
+    match (method.as_str(), segments.as_slice()) {
+        ("POST", ["auth", "login"]) => {
+            return if body["email"] == EMAIL && body["password"] == PASSWORD {
+                let token = portal.issue_token();
+                fixture(&json!({ "auth": token, "refresh": REFRESH_TOKEN }).to_string())
+            } else {
+                (StatusCode::UNAUTHORIZED, "bad credentials").into_response()
+            };
+        }
+        ("POST", ["auth", "refresh"]) => {
+            let allowed = portal.state.lock().unwrap().refresh_allowed;
+            return if allowed && body["refresh_token"] == REFRESH_TOKEN {
+                let token = portal.issue_token();
+                fixture(&json!({ "auth": token }).to_string())
+            } else {
+                (StatusCode::UNAUTHORIZED, "refresh token rejected").into_response()
+            };
+        }
+        ("POST", ["billing", "calculate"]) => return fixture(BILLING),
+        _ => {}
+    }
+
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default()
+        .to_string();
+    if bearer != portal.token() {
+        return (StatusCode::UNAUTHORIZED, "token expired or invalid").into_response();
+    }
+
+    match (method.as_str(), segments.as_slice()) {
+        ("GET", ["nodes"]) => fixture(NODES),
+        ("GET", ["node", "3123807602741248"]) => fixture(NODE),
+        ("GET", ["node", id]) => not_found(id),
+        ("GET", ["runners"]) => fixture(RUNNERS),
+        ("GET", ["runner", name]) if known_runner(name) => fixture(RUNNER),
+        ("GET", ["runner", name]) => not_found(name),
+        ("GET", ["usage", "group" | "single", _]) => fixture(USAGE),
+        ("GET", ["logs", "inst-web-a", _]) => fixture(LOGS),
+        ("GET", ["logs", id, _]) => not_found(id),
+        ("GET", ["control", runner, command]) if known_runner(runner) => payload(json!({
+            "runnerId": runner,
+            "commandId": format!("cmd-{}", now()),
+            "command": command,
+            "params": null,
+            "queued_at": now(),
+            "status": "Starting",
+        })),
+        ("GET", ["control", runner, _]) => not_found(runner),
+        ("GET", ["discover"]) => payload(Value::Null),
+        ("GET", ["account", "me"]) => fixture(&json!({ "user_id": "ops" }).to_string()),
+        ("POST", ["whoami"]) => {
+            fixture(&json!({ "you": { "roles": "user", "expires": 3600 } }).to_string())
+        }
+        _ => not_found(&path),
+    }
+}
+
+/// A scratch `$HOME` with `~/.artisan_cli/.env` pointing at a mock portal.
+pub struct TestHome {
+    pub dir: PathBuf,
+    portal: MockPortal,
+}
+
+impl TestHome {
+    /// Logged in with the token the portal currently accepts.
+    pub fn new(portal: &MockPortal) -> TestHome {
+        let home = TestHome::empty(portal);
+        home.write_env(&portal.token(), REFRESH_TOKEN);
+        home
+    }
+
+    /// Never logged in, no `.env` at all.
+    pub fn empty(portal: &MockPortal) -> TestHome {
+        let dir = std::env::temp_dir().join(format!(
+            "artisan_cli_test_{}_{}",
+            std::process::id(),
+            HOMES_CREATED.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join(".artisan_cli")).expect("create test home");
+        TestHome {
+            dir,
+            portal: portal.clone(),
+        }
+    }
+
+    pub fn write_env(&self, token: &str, refresh_token: &str) {
+        fs::write(
+            self.env_path(),
+            format!("API_TOKEN={}\nREFRESH_TOKEN={}\n", token, refresh_token),
+        )
+        .expect("write .env");
+    }
+
+    pub fn env_path(&self) -> PathBuf {
+        self.dir.join(".artisan_cli").join(".env")
+    }
+
+    pub fn env_file(&self) -> String {
+        fs::read_to_string(self.env_path()).unwrap_or_default()
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.dir.join(".artisan_cli").join("config.toml")
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_artisan_cli"));
+        command
+            .args(args)
+            .env("HOME", &self.dir)
+            .env("ARTISAN_API_URL", self.portal.url())
+            .env("NO_COLOR", "1")
+            .env("NO_PROXY", "127.0.0.1")
+            .env_remove("API_TOKEN")
+            .env_remove("REFRESH_TOKEN")
+            .env_remove("ARTISAN_PROFILE")
+            .env_remove("ARTISAN_PROXY")
+            .env_remove("ARTISAN_CA_BUNDLE")
+            .env_remove("ARTISAN_CLIENT_CERT")
+            .env_remove("ARTISAN_CLIENT_KEY")
+            .env_remove("COMPLETE");
+        command
+    }
+
+    /// Runs the CLI with `args`, stdout and stderr are returned together.
+    pub fn run(&self, args: &[&str]) -> (bool, String) {
+        let output = self.command(args).output().expect("run artisan_cli");
+        combined(output)
+    }
+
+    /// Like `run`, with `input` piped to stdin.
+    pub fn run_with_input(&self, args: &[&str], input: &str) -> (bool, String) {
+        let mut child = self
+            .command(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("run artisan_cli");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        combined(child.wait_with_output().expect("run artisan_cli"))
+    }
+}
+
+fn combined(output: Output) -> (bool, String) {
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    (output.status.success(), text)
+}
+
+impl Drop for TestHome {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use std::fs;
+
+use common::{MockPortal, TestHome};
+
+#[test]
+fn set_get_and_unset_round_trip() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["config", "get", "http.timeout"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("30"), "{}", out);
+
+    let (ok, out) = home.run(&["config", "set", "http.timeout", "45"]);
+    assert!(ok, "{}", out);
+    let (_, out) = home.run(&["config", "get", "http.timeout"]);
+    assert!(out.contains("45"), "{}", out);
+    let config = fs::read_to_string(home.config_path()).unwrap();
+    assert!(
+        config.contains("[http]") && config.contains("timeout = 45"),
+        "{}",
+        config
+    );
+
+    let (ok, out) = home.run(&["config", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("config.toml"), "{}", out);
+
+    let (ok, out) = home.run(&["config", "unset", "http.timeout"]);
+    assert!(ok, "{}", out);
+    let (_, out) = home.run(&["config", "get", "http.timeout"]);
+    assert!(out.contains("30"), "{}", out);
+}
+
+#[test]
+fn invalid_values_are_not_saved() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["config", "set", "http.timeout", "soon"]);
+    assert!(!ok);
+    assert!(out.contains("whole number"), "{}", out);
+
+    let (ok, _) = home.run(&["config", "set", "color", "purple"]);
+    assert!(!ok);
+
+    let (ok, out) = home.run(&["config", "set", "colour", "never"]);
+    assert!(!ok);
+    assert!(out.contains("Unknown key"), "{}", out);
+
+    assert!(!home.config_path().exists());
+}
+
+#[test]
+fn broken_file_is_reported_and_ignored() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+    fs::write(home.config_path(), "log_levle = \"info\"\n").unwrap();
+
+    let (ok, out) = home.run(&["config", "validate"]);
+    assert!(!ok);
+    assert!(out.contains("log_levle"), "{}", out);
+
+    let (ok, out) = home.run(&["node", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("Ignoring config.toml"), "{}", out);
+    assert!(out.contains("3123807602741248"), "{}", out);
+}
+
+#[test]
+fn settings_apply_to_commands() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["config", "set", "output", "raw"]);
+    assert!(ok, "{}", out);
+    let (ok, out) = home.run(&["runner", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("273600"), "{}", out);
+}
+
+#[test]
+fn legacy_env_settings_are_migrated() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+    let env = home.env_file();
+    fs::write(home.env_path(), format!("{}ARTISAN_PROFILE=staging\n", env)).unwrap();
+
+    let (ok, out) = home.run(&["config", "get", "profile"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("staging"), "{}", out);
+    assert!(!home.env_file().contains("ARTISAN_PROFILE"));
+    assert!(home.env_file().contains("API_TOKEN"));
+}
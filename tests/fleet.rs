@@ -0,0 +1,80 @@
+mod common;
+
+use std::fs;
+
+use common::{MockPortal, TestHome};
+
+const FLEET_YAML: &str = "runners:\n  ais_web: running\n  worker: stopped\n";
+
+fn manifest(home: &TestHome, name: &str, contents: &str) -> String {
+    let path = home.dir.join(name);
+    fs::write(&path, contents).unwrap();
+    path.display().to_string()
+}
+
+#[test]
+fn plan_only_lists_needed_changes() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+    let file = manifest(&home, "fleet.yaml", FLEET_YAML);
+
+    let (ok, out) = home.run(&["plan", "-f", &file]);
+    assert!(ok, "{}", out);
+    assert!(
+        out.contains("Plan: 0 to start, 1 to stop, 1 unchanged"),
+        "{}",
+        out
+    );
+    assert!(portal.hits().contains(&"GET runner/ais_worker".to_string()));
+    assert_eq!(portal.hit_count("GET control/ais_worker/stop"), 0);
+}
+
+#[test]
+fn apply_queues_changes_and_writes_a_summary() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+    let file = manifest(
+        &home,
+        "fleet.toml",
+        "[runners]\nais_web = \"running\"\nais_worker = \"stopped\"\n",
+    );
+
+    let (ok, out) = home.run(&["apply", "-f", &file, "--yes"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("[1/1] stop ais_worker ... queued"), "{}", out);
+    assert_eq!(portal.hit_count("GET control/ais_worker/stop"), 1);
+    assert_eq!(portal.hit_count("GET control/ais_web/start"), 0);
+
+    let summaries = fs::read_dir(home.dir.join(".artisan_cli").join("fleet"))
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(summaries.len(), 1);
+}
+
+#[test]
+fn apply_needs_confirmation() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+    let file = manifest(&home, "fleet.yaml", FLEET_YAML);
+
+    let (ok, out) = home.run(&["apply", "-f", &file]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("Apply cancelled"), "{}", out);
+    assert_eq!(portal.hit_count("GET control/ais_worker/stop"), 0);
+}
+
+#[test]
+fn apply_refuses_unknown_runners() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+    let file = manifest(
+        &home,
+        "fleet.yaml",
+        "runners:\n  worker: stopped\n  ais_ghost: running\n",
+    );
+
+    let (ok, out) = home.run(&["apply", "-f", &file, "--yes"]);
+    assert!(!ok);
+    assert!(out.contains("ais_ghost"), "{}", out);
+    assert_eq!(portal.hit_count("GET control/ais_worker/stop"), 0);
+}
@@ -0,0 +1,301 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{jwt, now, MockPortal, TestHome, EMAIL, PASSWORD, REFRESH_TOKEN};
+
+#[test]
+fn node_list_shows_every_node() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["node", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("3123807602741248"), "{}", out);
+    assert!(out.contains("3123807603281920"), "{}", out);
+    assert!(out.contains("10.0.0.12"), "{}", out);
+    assert_eq!(portal.hit_count("GET nodes"), 1);
+}
+
+#[test]
+fn node_get_shows_requested_sections() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["node", "get", "3123807602741248", "--section", "manager"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("edge-ams-01"), "{}", out);
+    assert!(portal
+        .hits()
+        .contains(&"GET node/3123807602741248".to_string()));
+}
+
+#[test]
+fn node_get_reports_unknown_nodes() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (_, out) = home.run(&["node", "get", "9999"]);
+    assert!(out.contains("Failed to get node details"), "{}", out);
+}
+
+#[test]
+fn runner_list_strips_prefix_and_humanizes_uptime() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["runner", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("worker"), "{}", out);
+    assert!(!out.contains("ais_web"), "{}", out);
+    assert!(out.contains("3d 4h"), "{}", out);
+
+    let (ok, out) = home.run(&["--raw", "runner", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("273600"), "{}", out);
+}
+
+#[test]
+fn runner_details_lists_instances() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["runner", "details", "ais_web"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("inst-web-a"), "{}", out);
+    assert!(out.contains("inst-web-b"), "{}", out);
+    assert!(out.contains("12.5%"), "{}", out);
+}
+
+#[test]
+fn usage_hits_group_and_single_endpoints() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["runner", "usage", "ais_web"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("88.00%"), "{}", out);
+
+    let (ok, out) = home.run(&["instance", "usage", "inst-web-a"]);
+    assert!(ok, "{}", out);
+    assert!(portal
+        .hits()
+        .contains(&"GET usage/group/ais_web".to_string()));
+    assert!(portal
+        .hits()
+        .contains(&"GET usage/single/inst-web-a".to_string()));
+}
+
+#[test]
+fn bill_prices_the_usage_summary() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["runner", "bill", "ais_web"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("$1.25"), "{}", out);
+    assert!(out.contains("$2.50"), "{}", out);
+    assert_eq!(portal.hit_count("POST billing/calculate"), 1);
+}
+
+#[test]
+fn logs_report_unknown_instances() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    // Known instances open the full screen viewer, which needs a terminal
+    let (_, out) = home.run(&["logs", "inst-missing", "--lines", "5"]);
+    assert!(out.contains("Failed to list logs"), "{}", out);
+    assert_eq!(portal.hit_count("GET logs/inst-missing/5"), 1);
+}
+
+#[test]
+fn control_queues_the_command() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["runner", "control", "ais_web", "restart"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("Executed: restart"), "{}", out);
+    assert_eq!(portal.hit_count("GET control/ais_web/restart"), 1);
+}
+
+#[test]
+fn control_is_not_retried_unless_unsafe() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    portal.fail("control/ais_web/restart", &[StatusCode::BAD_GATEWAY]);
+    let (_, out) = home.run(&["runner", "control", "ais_web", "restart"]);
+    assert!(out.contains("Failed to control runner"), "{}", out);
+    assert_eq!(portal.hit_count("GET control/ais_web/restart"), 1);
+
+    portal.fail("control/ais_web/restart", &[StatusCode::BAD_GATEWAY]);
+    let (ok, out) = home.run(&["--retry-unsafe", "runner", "control", "ais_web", "restart"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("Executed: restart"), "{}", out);
+    assert_eq!(portal.hit_count("GET control/ais_web/restart"), 3);
+}
+
+#[test]
+fn gets_are_retried_after_transient_failures() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    portal.fail(
+        "runners",
+        &[StatusCode::BAD_GATEWAY, StatusCode::SERVICE_UNAVAILABLE],
+    );
+    let (ok, out) = home.run(&["runner", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("worker"), "{}", out);
+    assert_eq!(portal.hit_count("GET runners"), 3);
+}
+
+#[test]
+fn expired_token_is_refreshed() {
+    let portal = MockPortal::start();
+    let home = TestHome::empty(&portal);
+    home.write_env(&jwt(now() - 60), REFRESH_TOKEN);
+
+    let (ok, out) = home.run(&["node", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("3123807602741248"), "{}", out);
+    assert_eq!(portal.hit_count("POST auth/refresh"), 1);
+    assert!(home.env_file().contains(&portal.token()));
+}
+
+#[test]
+fn rejected_refresh_logs_back_in() {
+    let portal = MockPortal::start();
+    let home = TestHome::empty(&portal);
+
+    let (ok, out) = home.run(&["auth", "login", EMAIL, PASSWORD]);
+    assert!(ok, "{}", out);
+
+    home.write_env(&jwt(now() - 60), "revoked");
+    portal.reject_refresh();
+
+    let (ok, out) = home.run(&["runner", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("worker"), "{}", out);
+    assert_eq!(portal.hit_count("POST auth/refresh"), 1);
+    assert_eq!(portal.hit_count("POST auth/login"), 2);
+    assert!(home.env_file().contains(&portal.token()));
+}
+
+#[test]
+fn missing_token_asks_to_log_in() {
+    let portal = MockPortal::start();
+    let home = TestHome::empty(&portal);
+
+    let (ok, out) = home.run(&["node", "list"]);
+    assert!(!ok);
+    assert!(out.contains("Token not found"), "{}", out);
+    assert!(portal.hits().is_empty());
+}
+
+#[test]
+fn rejected_token_is_reported() {
+    let portal = MockPortal::start();
+    let home = TestHome::empty(&portal);
+    home.write_env("not-a-jwt", REFRESH_TOKEN);
+
+    let (_, out) = home.run(&["node", "list"]);
+    assert!(out.contains("Failed to list nodes"), "{}", out);
+    assert!(!out.contains("3123807602741248"), "{}", out);
+}
+
+#[test]
+fn offline_serves_the_last_response() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, _) = home.run(&["node", "list"]);
+    assert!(ok);
+
+    let (ok, out) = home.run(&["--offline", "node", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("3123807602741248"), "{}", out);
+    assert!(out.contains("OFFLINE"), "{}", out);
+    assert_eq!(portal.hit_count("GET nodes"), 1);
+
+    let (ok, out) = home.run(&["--offline", "runner", "list"]);
+    assert!(!ok);
+    assert!(out.contains("No cached copy of runners"), "{}", out);
+}
+
+#[test]
+fn trace_http_redacts_secrets() {
+    let portal = MockPortal::start();
+    let home = TestHome::empty(&portal);
+
+    let (ok, out) = home.run(&["--trace-http", "auth", "login", EMAIL, PASSWORD]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("> POST http://127.0.0.1"), "{}", out);
+    assert!(out.contains("< 200 OK in"), "{}", out);
+    assert!(!out.contains(PASSWORD), "{}", out);
+    assert!(!out.contains(REFRESH_TOKEN), "{}", out);
+
+    let (ok, out) = home.run(&["--trace-http", "node", "list"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("/v1/nodes"), "{}", out);
+    assert!(out.contains("authorization: <redacted>"), "{}", out);
+    assert!(!out.contains(&portal.token()), "{}", out);
+    assert!(out.contains("3123807603281920"), "{}", out);
+}
+
+#[test]
+fn doctor_reports_a_healthy_setup() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["doctor"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("API reachable"), "{}", out);
+    assert!(out.contains("in sync with the API"), "{}", out);
+    assert!(out.contains("credentials.ejson"), "{}", out);
+    assert_eq!(portal.hit_count("GET discover"), 1);
+}
+
+#[test]
+fn doctor_fails_on_an_unusable_token() {
+    let portal = MockPortal::start();
+    let home = TestHome::empty(&portal);
+    home.write_env(&jwt(now() - 600), "");
+
+    let (ok, out) = home.run(&["doctor", "--json"]);
+    assert!(!ok);
+    assert!(out.contains("\"status\": \"failed\""), "{}", out);
+    assert!(out.contains("no REFRESH_TOKEN"), "{}", out);
+    assert!(out.contains("artisan_cli auth login"), "{}", out);
+}
+
+#[test]
+fn mutating_calls_are_audited() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run(&["runner", "control", "ais_web", "restart"]);
+    assert!(ok, "{}", out);
+    let (ok, out) = home.run(&["runner", "control", "ais_ghost", "stop"]);
+    assert!(!ok, "{}", out);
+
+    let audit = std::fs::read_to_string(home.dir.join(".artisan_cli").join("audit.jsonl")).unwrap();
+    assert_eq!(audit.lines().count(), 2, "{}", audit);
+    assert!(audit.contains("\"user_id\":\"ops\""), "{}", audit);
+    assert!(
+        audit.contains("\"command\":\"control restart\""),
+        "{}",
+        audit
+    );
+    assert!(audit.contains("\"result\":\"failed\""), "{}", audit);
+
+    let (ok, out) = home.run(&["audit", "list", "--since", "1h"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("ais_ghost"), "{}", out);
+
+    let (ok, out) = home.run(&["audit", "show", "1"]);
+    assert!(ok, "{}", out);
+    assert!(out.contains("#1 control restart ais_web"), "{}", out);
+    assert!(out.contains("queued at"), "{}", out);
+}
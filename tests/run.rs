@@ -0,0 +1,83 @@
+mod common;
+
+use std::fs;
+
+use common::{MockPortal, TestHome};
+
+const SCRIPT: &str =
+    "# nightly check\nnode list\n\nartisan_cli config get bogus\nrunner list --sort \"name\"\n";
+
+fn script(home: &TestHome, contents: &str) -> String {
+    let path = home.dir.join("nightly.art");
+    fs::write(&path, contents).unwrap();
+    path.display().to_string()
+}
+
+#[test]
+fn run_stops_at_the_first_failure() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+    let file = script(&home, SCRIPT);
+
+    let (ok, out) = home.run(&["run", &file]);
+    assert!(!ok, "{}", out);
+    assert!(out.contains("1 ok, 1 failed, 1 skipped"), "{}", out);
+    assert_eq!(portal.hit_count("GET nodes"), 1);
+    assert_eq!(portal.hit_count("GET runners"), 0);
+}
+
+#[test]
+fn run_can_continue_on_error() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+    let file = script(&home, SCRIPT);
+
+    let (ok, out) = home.run(&["run", &file, "--continue-on-error"]);
+    assert!(!ok, "{}", out);
+    assert!(out.contains("2 ok, 1 failed, 0 skipped"), "{}", out);
+    assert_eq!(portal.hit_count("GET runners"), 1);
+}
+
+#[test]
+fn run_stops_when_an_api_call_fails() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+    let file = script(&home, "node get 9999\nrunner list\n");
+
+    let (ok, out) = home.run(&["run", &file]);
+    assert!(!ok, "{}", out);
+    assert!(out.contains("0 ok, 1 failed, 1 skipped"), "{}", out);
+    assert_eq!(portal.hit_count("GET node/9999"), 1);
+    assert_eq!(portal.hit_count("GET runners"), 0);
+}
+
+#[test]
+fn run_refuses_commands_that_never_return() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    for line in [
+        "shell",
+        "exporter",
+        "watch-events",
+        "metrics record ais_web",
+    ] {
+        let file = script(&home, &format!("node list\n{}\n", line));
+        let (ok, out) = home.run(&["run", &file]);
+        assert!(!ok, "{}", out);
+        assert!(out.contains("line 2: scripts can't call"), "{}", out);
+    }
+    assert_eq!(portal.hit_count("GET nodes"), 0);
+}
+
+#[test]
+fn run_checks_every_line_before_starting() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+    let file = script(&home, "node list\nrunner lisst\n");
+
+    let (ok, out) = home.run(&["run", &file]);
+    assert!(!ok);
+    assert!(out.contains("line 2"), "{}", out);
+    assert_eq!(portal.hit_count("GET nodes"), 0);
+}
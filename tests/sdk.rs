@@ -0,0 +1,57 @@
+mod common;
+
+use artisan_cli::sdk::{token_expiry, ArtisanClient};
+use common::{jwt, now, MockPortal, EMAIL, PASSWORD, REFRESH_TOKEN};
+use reqwest::Client;
+
+fn client(portal: &MockPortal) -> ArtisanClient {
+    let http = Client::builder().no_proxy().build().unwrap();
+    ArtisanClient::with_base_url(portal.url())
+        .unwrap()
+        .with_http_client(http)
+}
+
+#[tokio::test]
+async fn login_then_typed_endpoints() {
+    let portal = MockPortal::start();
+    let sdk = client(&portal);
+    sdk.login(EMAIL, PASSWORD).await.unwrap();
+
+    let nodes = sdk.nodes().await.unwrap();
+    assert_eq!(nodes.len(), 2);
+
+    let instances = sdk.runner("ais_web").await.unwrap();
+    assert_eq!(instances.len(), 2);
+
+    let usage = sdk.runner_usage("ais_web").await.unwrap();
+    let costs = sdk.calculate_billing(&usage).await.unwrap();
+    assert_eq!(costs.ram_cost, 1.25);
+
+    assert!(portal
+        .hits()
+        .contains(&"GET usage/group/ais_web".to_string()));
+}
+
+#[tokio::test]
+async fn errors_carry_the_path_and_status() {
+    let portal = MockPortal::start();
+    let sdk = client(&portal).with_session(&portal.token(), REFRESH_TOKEN);
+
+    let err = sdk.node("9999").await.unwrap_err().to_string();
+    assert!(err.contains("node/9999 returned 404"), "{}", err);
+
+    assert!(client(&portal).nodes().await.is_err());
+}
+
+#[tokio::test]
+async fn expired_session_is_refreshed_once() {
+    let portal = MockPortal::start();
+    let sdk = client(&portal).with_session(&jwt(now() - 60), REFRESH_TOKEN);
+
+    sdk.runners().await.unwrap();
+    sdk.control("ais_web", "restart").await.unwrap();
+
+    assert_eq!(portal.hit_count("POST auth/refresh"), 1);
+    assert_eq!(sdk.session().unwrap().token, portal.token());
+    assert!(token_expiry(&portal.token()).unwrap() > now());
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use common::{MockPortal, TestHome};
+
+#[test]
+fn shell_runs_commands_with_the_used_context() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run_with_input(
+        &["shell"],
+        "node list\nuse runner ais_web\nuse node 3123807602741248\nrunner details\nnode get\nexit\nauth discover\n",
+    );
+    assert!(ok, "{}", out);
+    assert_eq!(portal.hit_count("GET nodes"), 1);
+    assert_eq!(portal.hit_count("GET runner/ais_web"), 1);
+    assert_eq!(portal.hit_count("GET node/3123807602741248"), 1);
+    // nothing after `exit` runs
+    assert_eq!(portal.hit_count("GET discover"), 0);
+}
+
+#[test]
+fn shell_keeps_going_after_a_bad_line() {
+    let portal = MockPortal::start();
+    let home = TestHome::new(&portal);
+
+    let (ok, out) = home.run_with_input(&["shell"], "node lisst\nrunner details\nnode list\n");
+    assert!(ok, "{}", out);
+    assert!(out.contains("lisst"), "{}", out);
+    assert_eq!(portal.hit_count("GET nodes"), 1);
+}